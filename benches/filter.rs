@@ -0,0 +1,130 @@
+// Benchmark of filtering synthetic item lists
+//
+// Run with `cargo bench --bench filter`, optionally with a list of sizes,
+// e.g. `cargo bench --bench filter -- 1000 50000`
+
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use minions::mcore::{item::Item, matcher::Matcher, scorer::ScorerKind};
+
+const WORDS: &[&str] = &[
+    "hello",
+    "world",
+    "terminal",
+    "browser",
+    "file",
+    "manager",
+    "settings",
+    "music",
+    "player",
+    "editor",
+    "Calculator",
+    "GitHub",
+    "screenshot",
+    "network",
+    "你好",
+    "世界",
+    "文件",
+    "设置",
+    "终端",
+    "音乐",
+];
+
+const PATTERNS: &[&str] = &["e", "fm", "term", "hw", "nihao", "xyz"];
+
+const SCORERS: &[ScorerKind] = &[
+    ScorerKind::Fuzzy,
+    ScorerKind::Fzf,
+    ScorerKind::Prefix,
+    ScorerKind::Substring,
+];
+
+/// Deterministic pseudo random numbers (LCG)
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        ((self.0 >> 33) as usize) % n
+    }
+
+    fn words(&mut self, count: usize) -> String {
+        (0..count)
+            .map(|_| WORDS[self.next(WORDS.len())])
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn items(n: usize) -> Vec<Rc<Item>> {
+    let mut random = Random(n as u64);
+    (0..n)
+        .map(|idx| {
+            Rc::new(Item {
+                title: format!("{} {}", random.words(2), idx),
+                subtitle: Some(random.words(5)),
+                badge: Some(random.words(1)),
+                keywords: vec![random.words(1), random.words(1)],
+                ..Item::default()
+            })
+        })
+        .collect()
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn main() {
+    let sizes = std::env::args()
+        .skip(1)
+        .filter_map(|x| x.parse::<usize>().ok())
+        .collect::<Vec<_>>();
+    let sizes = if sizes.is_empty() {
+        vec![1_000, 10_000, 100_000]
+    } else {
+        sizes
+    };
+    let path = std::env::temp_dir().join(format!("minions-bench-{}.dat", std::process::id()));
+    let matcher = Matcher::new(&path, "salt", None).expect("Unable to create matcher");
+
+    println!(
+        "{:>8} {:>10} {:>8} {:>10} {:>10} {:>8}",
+        "items", "scorer", "pattern", "first(ms)", "avg(ms)", "matched"
+    );
+    for &size in sizes.iter() {
+        for &scorer in SCORERS.iter() {
+            // new list for every scorer, so the first run includes preparing
+            let items = items(size);
+            for pattern in PATTERNS.iter() {
+                let start = Instant::now();
+                let matched = matcher.sort(pattern, &items, scorer).len();
+                let first = start.elapsed();
+
+                let runs = 5;
+                let start = Instant::now();
+                for _ in 0..runs {
+                    matcher.sort(pattern, &items, scorer);
+                }
+                let avg = start.elapsed() / runs;
+                println!(
+                    "{:>8} {:>10} {:>8} {:>10.2} {:>10.2} {:>8}",
+                    size,
+                    format!("{:?}", scorer),
+                    pattern,
+                    millis(first),
+                    millis(avg),
+                    matched
+                );
+            }
+        }
+    }
+
+    let _ = std::fs::remove_file(&path);
+}
@@ -1,11 +1,31 @@
 extern crate serde_json;
 extern crate shlex;
 
-use std::{path::PathBuf, process::Command};
+extern crate nix;
+
+use std::{
+    cell::RefCell,
+    io::{BufRead, BufReader, Read},
+    os::unix::process::CommandExt,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use self::nix::{
+    sys::signal::{killpg, Signal},
+    unistd::{setsid, Pid},
+};
 
 use crate::mcore::{
     action::{Action, ActionResult},
+    cancel,
     errors::*,
+    item::Item,
 };
 
 use super::item::{ScriptItem, ScriptOutputFormat};
@@ -21,6 +41,8 @@ pub struct ScriptAction {
     pub action_run_realtime: bool,
 
     pub action_suggest_arg_scope: Option<String>,
+
+    pub timeout: Option<Duration>,
 }
 
 impl Action for ScriptAction {
@@ -43,6 +65,10 @@ impl Action for ScriptAction {
         }
     }
 
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
     fn run_bare(&self) -> ActionResult {
         self.run_action(None, "bare")
     }
@@ -54,6 +80,14 @@ impl Action for ScriptAction {
     fn run_arg_realtime(&self, text: &str) -> ActionResult {
         self.run_action(Some(text), "realtime")
     }
+
+    fn run_bare_streaming(&self, emit: &dyn Fn(Vec<Item>)) -> Result<()> {
+        self.run_action_streaming(None, "bare", emit)
+    }
+
+    fn run_arg_streaming(&self, text: &str, emit: &dyn Fn(Vec<Item>)) -> Result<()> {
+        self.run_action_streaming(Some(text), "text", emit)
+    }
 }
 
 fn parse_json(output: &[u8]) -> Result<Vec<ScriptItem>> {
@@ -92,8 +126,60 @@ fn parse_plain_text(output: &[u8]) -> Result<Vec<ScriptItem>> {
     Ok(ret)
 }
 
+fn parse_json_line(line: &str) -> Result<ScriptItem> {
+    serde_json::from_str(line).map_err(|e| Error::with_chain(e, "Error parsing JSON line"))
+}
+
 impl ScriptAction {
     fn run_action(&self, arg: Option<&str>, typ: &str) -> ActionResult {
+        let items = RefCell::new(Vec::new());
+        self.run_action_streaming(arg, typ, &|batch| items.borrow_mut().extend(batch))?;
+        Ok(items.into_inner())
+    }
+
+    /// Read output of the script, emit items as soon as they're parsed
+    fn read_output(&self, output: impl Read, emit: &dyn Fn(Vec<Item>)) -> Result<()> {
+        if self.action_output_format == ScriptOutputFormat::JsonLines {
+            // one item per line, emitted immediately
+            for line in BufReader::new(output).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let item = parse_json_line(&line)?;
+                emit(vec![item.into_item(&self.script_dir)]);
+            }
+            return Ok(());
+        }
+
+        let mut output = BufReader::new(output);
+        let mut buf = Vec::new();
+        output.read_to_end(&mut buf)?;
+        let items = if buf.is_empty() {
+            Vec::new()
+        } else {
+            match self.action_output_format {
+                ScriptOutputFormat::Json => parse_json(&buf),
+                ScriptOutputFormat::EscapedText => parse_escaped_text(&buf),
+                ScriptOutputFormat::PlainText => parse_plain_text(&buf),
+                ScriptOutputFormat::JsonLines => unreachable!(),
+            }?
+        };
+        emit(
+            items
+                .into_iter()
+                .map(|x| x.into_item(&self.script_dir))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    fn run_action_streaming(
+        &self,
+        arg: Option<&str>,
+        typ: &str,
+        emit: &dyn Fn(Vec<Item>),
+    ) -> Result<()> {
         // TODO: support some special commands, like copy, open, etc.
         let cmdline = shlex::split(&self.action);
         if cmdline.is_none() {
@@ -112,20 +198,63 @@ impl ScriptAction {
         cmd.env("MINIONS_RUN_TYPE", typ);
         debug!("Running script action: {:?}", cmd);
 
-        let output = cmd.output()?.stdout;
-        let items = if output.len() == 0 {
-            Vec::new()
-        } else {
-            match self.action_output_format {
-                ScriptOutputFormat::Json => parse_json(&output),
-                ScriptOutputFormat::EscapedText => parse_escaped_text(&output),
-                ScriptOutputFormat::PlainText => parse_plain_text(&output),
-            }?
+        // run in its own process group, so that the whole group can be
+        // killed when cancelled
+        unsafe {
+            cmd.pre_exec(|| {
+                setsid().map_err(|_| std::io::Error::last_os_error())?;
+                Ok(())
+            });
+        }
+        cmd.stdin(Stdio::null()).stdout(Stdio::piped());
+        let mut child = cmd.spawn()?;
+
+        let handle = cancel::current();
+        let finished = Arc::new(AtomicBool::new(false));
+        let finished_ = finished.clone();
+        let pgid = Pid::from_raw(child.id() as i32);
+        handle.on_cancel(move || {
+            if !finished_.load(Ordering::SeqCst) {
+                debug!("Killing script process group {}", pgid);
+                let _ = killpg(pgid, Signal::SIGKILL);
+            }
+        });
+        let res = self.read_output(child.stdout.take().unwrap(), emit);
+        let status = child.wait();
+        finished.store(true, Ordering::SeqCst);
+        handle.check()?;
+        status?;
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_lines_test() {
+        let action = ScriptAction {
+            script_dir: PathBuf::from("."),
+            action: r#"printf '{"title": "a"}\n\n{"title": "b", "badge": "B"}\n'"#.into(),
+            action_output_format: ScriptOutputFormat::JsonLines,
+            action_run_bare: true,
+            action_run_arg: false,
+            action_run_realtime: false,
+            action_suggest_arg_scope: None,
+            timeout: None,
         };
+        let batches = RefCell::new(Vec::new());
+        action
+            .run_bare_streaming(&|items| batches.borrow_mut().push(items))
+            .unwrap();
+        let batches = batches.into_inner();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[1][0].title, "b");
+        assert_eq!(batches[1][0].badge, Some("B".into()));
 
-        Ok(items
-            .into_iter()
-            .map(|x| x.into_item(&self.script_dir))
-            .collect())
+        let items = action.run_bare().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title, "a");
     }
 }
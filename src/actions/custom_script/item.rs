@@ -1,6 +1,6 @@
-use std::{path::Path, sync::Arc};
+use std::{path::Path, sync::Arc, time::Duration};
 
-use crate::mcore::item::Item;
+use crate::mcore::item::{Alternative, Item};
 
 use super::{action::ScriptAction, parser::parse_icon};
 
@@ -8,10 +8,25 @@ use super::{action::ScriptAction, parser::parse_icon};
 #[serde(rename_all = "snake_case")]
 pub enum ScriptOutputFormat {
     Json,
+    /// One JSON object per line, each line is shown as soon as it's printed
+    JsonLines,
     EscapedText,
     PlainText,
 }
 
+fn default_output_format() -> ScriptOutputFormat {
+    ScriptOutputFormat::Json
+}
+
+/// Alternative action of script item, run without argument
+#[derive(Deserialize, Clone)]
+pub struct ScriptAlternative {
+    pub title: String,
+    pub action: String,
+    #[serde(default = "default_output_format")]
+    pub action_output_format: ScriptOutputFormat,
+}
+
 #[derive(Deserialize, Clone)]
 #[serde(default = "ScriptItem::default")]
 pub struct ScriptItem {
@@ -21,6 +36,10 @@ pub struct ScriptItem {
     pub icon: Option<String>,
     pub data: Option<String>,
     pub priority: i32,
+    /// Extra words to match
+    pub keywords: Vec<String>,
+    /// Keyword to run the action with argument directly, see `Item::trigger`
+    pub trigger: Option<String>,
 
     pub action: Option<String>,
     pub action_output_format: ScriptOutputFormat,
@@ -30,6 +49,10 @@ pub struct ScriptItem {
     pub action_run_realtime: bool,
 
     pub action_suggest_arg_scope: Option<String>,
+    /// Timeout in milliseconds, override `core.action_timeout`
+    pub timeout: Option<u64>,
+
+    pub alternatives: Vec<ScriptAlternative>,
 
     pub requirements: Vec<String>,
 }
@@ -43,12 +66,16 @@ impl ScriptItem {
             icon: None,
             data: None,
             priority: -20,
+            keywords: Vec::new(),
+            trigger: None,
             action: None,
             action_output_format: ScriptOutputFormat::Json,
             action_run_bare: true,
             action_run_arg: false,
             action_run_realtime: true,
             action_suggest_arg_scope: None,
+            timeout: None,
+            alternatives: Vec::new(),
             requirements: Vec::new(),
         }
     }
@@ -64,9 +91,29 @@ impl ScriptItem {
                 action_run_arg: self.action_run_arg,
                 action_run_realtime: self.action_run_realtime,
                 action_suggest_arg_scope: self.action_suggest_arg_scope,
+                timeout: self.timeout.map(Duration::from_millis),
             }),
         };
 
+        let timeout = self.timeout.map(Duration::from_millis);
+        let alternatives = self
+            .alternatives
+            .into_iter()
+            .map(|alternative| Alternative {
+                name: alternative.title,
+                action: Arc::new(ScriptAction {
+                    script_dir: script_dir.to_path_buf(),
+                    action: alternative.action,
+                    action_output_format: alternative.action_output_format,
+                    action_run_bare: true,
+                    action_run_arg: false,
+                    action_run_realtime: false,
+                    action_suggest_arg_scope: None,
+                    timeout,
+                }),
+            })
+            .collect();
+
         let icon = match self.icon {
             Some(ref s) => parse_icon(&s, script_dir),
             None => None,
@@ -80,6 +127,9 @@ impl ScriptItem {
             priority: self.priority,
             data: self.data,
             search_str: None,
+            keywords: self.keywords,
+            trigger: self.trigger,
+            alternatives,
             action: match action {
                 Some(action) => Some(Arc::new(action)),
                 None => None,
@@ -11,11 +11,15 @@ use std::{
 use dirs;
 
 use crate::{
-    actions::utils::open,
+    actions::utils::{
+        clipboard::CopyAction,
+        open::{self, OpenAction},
+    },
     mcore::{
-        action::{Action, ActionResult},
+        action::{Action, ActionResult, PartialAction},
+        cancel,
         config::Config,
-        item::{Icon, Item},
+        item::{Alternative, Icon, Item},
     },
 };
 
@@ -41,6 +45,25 @@ impl FileBrowserEntry {
     }
 
     fn into_item(self) -> Item {
+        let path: String = self.path.to_string_lossy().into();
+        let mut alternatives = vec![Alternative {
+            name: "Copy path".into(),
+            action: Arc::new(PartialAction::new(
+                Arc::new(CopyAction {}),
+                path.clone(),
+                None,
+            )),
+        }];
+        if let Some(parent) = self.path.parent() {
+            alternatives.push(Alternative {
+                name: "Open containing folder".into(),
+                action: Arc::new(PartialAction::new(
+                    Arc::new(OpenAction {}),
+                    parent.to_string_lossy().into(),
+                    None,
+                )),
+            });
+        }
         Item {
             title: self.name.clone(),
             subtitle: Some(self.path.to_string_lossy().into()),
@@ -54,9 +77,10 @@ impl FileBrowserEntry {
             } else {
                 Icon::FontAwesome("folder".into())
             }),
-            data: Some(self.path.to_string_lossy().into()),
+            data: Some(path),
             priority: -10,
             action: Some(Arc::new(self)),
+            alternatives,
             ..Item::default()
         }
     }
@@ -76,7 +100,9 @@ impl Action for FileBrowserEntry {
 
             debug!("Reading dir: {:?}", self.path);
             let entries = self.path.read_dir()?;
+            let handle = cancel::current();
             for entry in entries.into_iter() {
+                handle.check()?;
                 match entry {
                     Ok(entry) => {
                         if let Some(act) = FileBrowserEntry::new(
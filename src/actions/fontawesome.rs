@@ -28,15 +28,22 @@ impl Action for FontAwesome {
                             .map(|x| x.as_str().unwrap())
                             .collect()
                     });
+                let title = x.1["label"].as_str().unwrap();
                 Item {
-                    title: x.1["label"].as_str().unwrap().into(),
+                    title: title.into(),
                     subtitle: search_terms.as_ref().map(|terms| terms.join(", ")),
                     icon: Some(Icon::FontAwesome(x.0.clone())),
                     badge: Some(format!("0x{}", x.1["unicode"].as_str().unwrap())),
                     data: Some(x.0.clone()),
+                    // statistics of items are recorded by this, keep it
                     search_str: search_terms
                         .as_ref()
                         .map(|terms| x.0.clone() + " " + &terms.join(" ")),
+                    keywords: std::iter::once(title)
+                        .chain(std::iter::once(x.0.as_str()))
+                        .chain(search_terms.unwrap_or_default())
+                        .map(String::from)
+                        .collect(),
                     ..Item::default()
                 }
             })
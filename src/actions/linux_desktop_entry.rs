@@ -14,20 +14,22 @@ use crate::{
         action::{Action, ActionResult},
         config::Config,
         errors::*,
-        item::{Icon, Item},
+        item::{Alternative, Icon, Item},
     },
 };
 use std::{ffi::OsStr, path::Path, sync::Arc};
 
 use error_chain::ChainedError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct LinuxDesktopEntry {
     name: String,
     comment: Option<String>,
     exec: Vec<String>,
     icon_text: Option<String>,
     terminal: bool,
+    /// `Keywords` and `GenericName`
+    keywords: Vec<String>,
 }
 
 impl Action for LinuxDesktopEntry {
@@ -95,6 +97,16 @@ impl LinuxDesktopEntry {
             bail!("Unsupported desktop entry type");
         }
 
+        let mut keywords: Vec<String> = config
+            .get_from_or(Some("Desktop Entry"), "Keywords", "")
+            .split(';')
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect();
+        if let Some(generic_name) = config.get_from(Some("Desktop Entry"), "GenericName") {
+            keywords.push(generic_name.into());
+        }
+
         let exec_str = config
             .get_from(Some("Desktop Entry"), "Exec")
             .ok_or(Error::from("No exec key found in desktop entry"))?;
@@ -115,6 +127,7 @@ impl LinuxDesktopEntry {
                 None => None,
             },
             terminal: config.get_from_or(Some("Desktop Entry"), "Terminal", "false") == "true",
+            keywords,
         })
     }
 
@@ -167,11 +180,23 @@ pub fn get(config: &Config) -> Vec<Item> {
                 None
             };
             let comment = action.comment.clone();
+            let alternatives = if action.terminal {
+                Vec::new()
+            } else {
+                vec![Alternative {
+                    name: "Run in terminal".into(),
+                    action: Arc::new(LinuxDesktopEntry {
+                        terminal: true,
+                        ..action.clone()
+                    }),
+                }]
+            };
 
             Item {
                 title: action.name.clone(),
                 data: exe_path,
                 subtitle: comment,
+                keywords: action.keywords.clone(),
                 badge: Some("Desktop Entry".into()),
                 icon: if let Some(ref icon_text) = action.icon_text {
                     Some(if icon_text.starts_with("/") {
@@ -183,6 +208,7 @@ pub fn get(config: &Config) -> Vec<Item> {
                     Some(Icon::GtkName("gtk-missing-image".into()))
                 },
                 action: Some(Arc::new(action)),
+                alternatives,
                 ..Item::default()
             }
         })
@@ -0,0 +1,284 @@
+// User-defined macros, which chain existing actions by name
+//
+// The first step runs bare (or with the quick-send reference), each following
+// step runs with the result of the previous step as argument.
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use crate::{
+    actions::custom_script::parser::parse_icon,
+    mcore::{
+        action::{Action, ActionResult, PartialAction},
+        cancel,
+        config::Config,
+        errors::*,
+        item::{Icon, Item},
+    },
+};
+
+#[derive(Deserialize)]
+struct MacroConfig {
+    /// Title of the macro item
+    name: String,
+    /// Titles of action items to run in order
+    steps: Vec<String>,
+    icon: Option<String>,
+    badge: Option<String>,
+    /// Keyword to run the macro with argument directly from the filter
+    trigger: Option<String>,
+    /// Let user choose from multiple results of a step, instead of taking
+    /// the first one
+    #[serde(default)]
+    choose: bool,
+}
+
+#[derive(Clone)]
+struct MacroStep {
+    name: String,
+    action: Arc<dyn Action + Sync + Send>,
+}
+
+struct MacroAction {
+    steps: Vec<MacroStep>,
+    choose: bool,
+    /// Global default timeout (`core.action_timeout`), for steps without their own
+    default_timeout: Option<Duration>,
+}
+
+impl Action for MacroAction {
+    fn runnable_bare(&self) -> bool {
+        self.steps[0].action.runnable_bare()
+    }
+
+    fn runnable_arg(&self) -> bool {
+        self.steps[0].action.runnable_arg()
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        // steps run in sequence, each with its own timeout or the global
+        // default, no timeout at all if any step is unlimited
+        self.steps
+            .iter()
+            .map(|step| step.action.timeout().or(self.default_timeout))
+            .sum()
+    }
+
+    fn run_bare(&self) -> ActionResult {
+        self.run_steps(None)
+    }
+
+    fn run_arg(&self, text: &str) -> ActionResult {
+        self.run_steps(Some(text))
+    }
+}
+
+impl MacroAction {
+    /// Macro action running the remaining steps after the first one
+    fn rest(&self) -> MacroAction {
+        MacroAction {
+            steps: self.steps[1..].to_vec(),
+            choose: self.choose,
+            default_timeout: self.default_timeout,
+        }
+    }
+
+    fn run_steps(&self, arg: Option<&str>) -> ActionResult {
+        let step = &self.steps[0];
+        debug!("Running macro step {:?} with {:?}", step.name, arg);
+        let mut items = match arg {
+            Some(arg) => step.action.run_arg(arg),
+            None => step.action.run_bare(),
+        }
+        .chain_err(|| format!("Failed running macro step {:?}", step.name))?;
+        if self.steps.len() == 1 {
+            return Ok(items);
+        }
+        cancel::current().check()?;
+
+        let rest = Arc::new(self.rest());
+        if self.choose && items.len() > 1 {
+            // let user choose the one to continue with
+            return Ok(items
+                .into_iter()
+                .map(|mut item| {
+                    let arg = item.data.clone().unwrap_or_else(|| item.title.clone());
+                    item.action = Some(Arc::new(PartialAction::new(rest.clone(), arg, None)));
+                    item.alternatives.clear();
+                    item
+                })
+                .collect());
+        }
+
+        if items.is_empty() {
+            bail!("Macro step {:?} returned nothing", step.name);
+        }
+        let item = items.swap_remove(0);
+        PartialAction::new(rest, item.data.unwrap_or(item.title), None).run_bare()
+    }
+}
+
+/// Get macro item, relative icon files are in `config_dir`
+fn get_macro(
+    config: MacroConfig,
+    items: &[Item],
+    default_timeout: Option<Duration>,
+    config_dir: &Path,
+) -> Result<Item> {
+    if config.steps.is_empty() {
+        bail!("No steps defined");
+    }
+    let mut steps = Vec::new();
+    for (idx, name) in config.steps.iter().enumerate() {
+        let action = items
+            .iter()
+            .find(|item| &item.title == name)
+            .and_then(|item| item.action.clone())
+            .ok_or_else(|| Error::from(format!("No action named {:?}", name)))?;
+        if idx > 0 && !action.runnable_arg() {
+            bail!("Action {:?} does not accept argument", name);
+        }
+        if idx == 0 && !(action.runnable_bare() || action.runnable_arg()) {
+            bail!("Action {:?} is not runnable", name);
+        }
+        steps.push(MacroStep {
+            name: name.clone(),
+            action,
+        });
+    }
+
+    Ok(Item {
+        title: config.name,
+        subtitle: Some(config.steps.join(" → ")),
+        badge: Some(config.badge.unwrap_or_else(|| "Macro".into())),
+        icon: config
+            .icon
+            .and_then(|x| parse_icon(&x, config_dir))
+            .or_else(|| Some(Icon::FontAwesome("forward".into()))),
+        action: Some(Arc::new(MacroAction {
+            steps,
+            choose: config.choose,
+            default_timeout,
+        })),
+        trigger: config.trigger,
+        ..Item::default()
+    })
+}
+
+/// Get macro items, composing actions in `items`
+pub fn get(config: &Config, items: &[Item]) -> Vec<Item> {
+    let default_timeout = match config.get::<u64>(&["core", "action_timeout"]).unwrap() {
+        0 => None,
+        ms => Some(Duration::from_millis(ms)),
+    };
+    config
+        .get::<Vec<MacroConfig>>(&["macros", "entries"])
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|macro_config| {
+            let name = macro_config.name.clone();
+            match get_macro(macro_config, items, default_timeout, config.dir()) {
+                Ok(item) => {
+                    debug!("Load macro: {}", name);
+                    Some(item)
+                }
+                Err(error) => {
+                    warn!("Unable to load macro {:?}: {}", name, error);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Repeat {}
+
+    impl Action for Repeat {
+        fn runnable_arg(&self) -> bool {
+            true
+        }
+
+        fn run_arg(&self, text: &str) -> ActionResult {
+            Ok(vec![
+                Item {
+                    title: text.repeat(2),
+                    ..Item::default()
+                },
+                Item {
+                    title: text.repeat(3),
+                    ..Item::default()
+                },
+            ])
+        }
+    }
+
+    #[test]
+    fn macro_test() {
+        let items = vec![Item {
+            title: "Repeat".into(),
+            action: Some(Arc::new(Repeat {})),
+            ..Item::default()
+        }];
+        let get_action = |choose, default_timeout| {
+            let item = get_macro(
+                MacroConfig {
+                    name: "Macro".into(),
+                    steps: vec!["Repeat".into(), "Repeat".into()],
+                    icon: None,
+                    badge: None,
+                    trigger: Some("rr".into()),
+                    choose,
+                },
+                &items,
+                default_timeout,
+                Path::new("/config"),
+            )
+            .unwrap();
+            assert_eq!(item.trigger.as_deref(), Some("rr"));
+            item.action.unwrap()
+        };
+
+        let action = get_action(false, Some(Duration::from_secs(1)));
+        assert_eq!(action.timeout(), Some(Duration::from_secs(2)));
+        assert_eq!(get_action(false, None).timeout(), None);
+        assert!(action.runnable_arg());
+        assert!(!action.runnable_bare());
+        let res = action.run_arg("a").unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].title, "aaaa");
+
+        let res = get_action(true, None).run_arg("a").unwrap();
+        assert_eq!(res[1].title, "aaa");
+        let res = res[1].action.as_ref().unwrap().run_bare().unwrap();
+        assert_eq!(res[1].title, "aaaaaaaaa");
+
+        let missing = MacroConfig {
+            name: "Macro".into(),
+            steps: vec!["Repeat".into(), "Missing".into()],
+            icon: None,
+            badge: None,
+            trigger: None,
+            choose: false,
+        };
+        assert!(get_macro(missing, &items, None, Path::new("/config")).is_err());
+
+        let with_icon = MacroConfig {
+            name: "Macro".into(),
+            steps: vec!["Repeat".into()],
+            icon: Some("file:icons/macro.png".into()),
+            badge: None,
+            trigger: None,
+            choose: false,
+        };
+        match get_macro(with_icon, &items, None, Path::new("/config"))
+            .unwrap()
+            .icon
+        {
+            Some(Icon::File(path)) => assert_eq!(path, Path::new("/config/icons/macro.png")),
+            _ => panic!("Expected file icon"),
+        }
+    }
+}
@@ -10,6 +10,7 @@ mod custom_script;
 mod file_browser;
 mod fontawesome;
 mod linux_desktop_entry;
+mod macros;
 mod reload;
 mod search_engine;
 mod wolframalpha;
@@ -25,11 +26,18 @@ pub fn get_action_items(config: &Config) -> Vec<Item> {
     ret.append(&mut linux_desktop_entry::get(config));
     ret.append(&mut custom_script::get(config));
 
-    ret.push(clipboard::get(config));
+    // clipboard is unavailable without display (e.g. in terminal frontend)
+    if gtk::is_initialized() {
+        ret.push(clipboard::get(config));
+        ret.push(clipboard::get_copy(config));
+    }
     ret.push(youdao::get(config));
     ret.push(wolframalpha::get(config));
     ret.push(reload::get(config));
     ret.push(fontawesome::get(config));
 
+    let mut macros = macros::get(config, &ret);
+    ret.append(&mut macros);
+
     ret
 }
@@ -1,14 +1,13 @@
-extern crate nix;
-
 use std::sync::Arc;
 
-use nix::unistd::Pid;
-
-use crate::mcore::{
-    action::{Action, ActionResult},
-    config::Config,
-    errors::*,
-    item::{Icon, Item},
+use crate::{
+    frontend::control::{self, Command},
+    mcore::{
+        action::{Action, ActionResult},
+        config::Config,
+        errors::*,
+        item::{Icon, Item},
+    },
 };
 
 struct ReloadAction {}
@@ -19,8 +18,9 @@ impl Action for ReloadAction {
     }
 
     fn run_bare(&self) -> ActionResult {
-        nix::sys::signal::kill(Pid::from_raw(0), nix::sys::signal::SIGHUP)
-            .map_err(|e| Error::with_chain(e, "Failed to send SIGHUP to myself"))?;
+        // reload this process, which may not be the one listening on the
+        // control socket (e.g. a foreground instance next to a daemon)
+        control::dispatch(Command::Reload).chain_err(|| "Failed to reload")?;
         Ok(Vec::new())
     }
 }
@@ -28,7 +28,7 @@ impl Action for ReloadAction {
 pub fn get(_: &Config) -> Item {
     Item {
         title: "Reload All Actions".into(),
-        subtitle: Some("Equivalent to `kill -HUP 0`".into()),
+        subtitle: Some("Reload config and all actions of this instance".into()),
         badge: Some("Minions".into()),
         priority: 100,
         icon: Some(Icon::FontAwesome("cog".into())),
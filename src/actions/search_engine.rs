@@ -12,15 +12,19 @@ use self::url::percent_encoding::{utf8_percent_encode, EncodeSet, DEFAULT_ENCODE
 use std::{
     path::Path,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
 use crate::{
-    actions::{custom_script::parser::parse_icon, utils::open},
+    actions::{
+        custom_script::parser::parse_icon,
+        utils::{clipboard::CopyAction, http, open},
+    },
     mcore::{
-        action::{Action, ActionResult},
+        action::{Action, ActionResult, PartialAction},
         config::Config,
         errors::*,
-        item::{Icon, Item},
+        item::{Alternative, Icon, Item},
     },
 };
 
@@ -45,6 +49,10 @@ struct SearchEngine {
     suggestion_url: Option<String>,
     /// Icon
     icon: Option<String>,
+    /// Keyword to search directly from the filter, e.g. "g" for "g query"
+    trigger: Option<String>,
+    /// Timeout in milliseconds, override `core.action_timeout`
+    timeout: Option<u64>,
 
     #[serde(skip, default = "default_suggestion_client")]
     suggestion_client: Arc<Mutex<reqwest::Client>>,
@@ -54,7 +62,7 @@ struct SearchEngine {
 }
 
 fn default_suggestion_client() -> Arc<Mutex<reqwest::Client>> {
-    Arc::new(Mutex::new(reqwest::Client::new()))
+    Arc::new(Mutex::new(http::client()))
 }
 
 impl Action for SearchEngine {
@@ -74,6 +82,10 @@ impl Action for SearchEngine {
         Some(&self.name)
     }
 
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout.map(Duration::from_millis)
+    }
+
     fn run_arg(&self, text: &str) -> ActionResult {
         let text = utf8_percent_encode(text, DEFAULT_PLUS_ENCODE_SET).to_string();
         let url = self.address.replace("%s", &text);
@@ -85,20 +97,15 @@ impl Action for SearchEngine {
         let text = utf8_percent_encode(text, DEFAULT_PLUS_ENCODE_SET).to_string();
         let url = self.suggestion_url.as_ref().unwrap().replace("%s", &text);
 
-        let result = if let Ok(client) = self.suggestion_client.try_lock() {
-            client
-                .get(&url)
-                .send()
-                .map_err(|e| Error::with_chain(e, "Suggestion request send failed"))?
-                .text()
-                .map_err(|e| Error::with_chain(e, "Suggestion reply decode failed"))?
-        } else {
-            warn!("Unable to use shared reqwest client!");
-            reqwest::get(&url)
-                .map_err(|e| Error::with_chain(e, "Suggestion request send failed"))?
-                .text()
-                .map_err(|e| Error::with_chain(e, "Suggestion reply decode failed"))?
+        let client = match self.suggestion_client.try_lock() {
+            Ok(client) => client.clone(),
+            Err(_) => {
+                warn!("Unable to use shared reqwest client!");
+                http::client()
+            }
         };
+        let result = http::get_text(client, &url)
+            .map_err(|e| Error::with_chain(e, "Suggestion request failed"))?;
 
         let result: serde_json::Value = serde_json::from_str(&result)
             .map_err(|e| Error::with_chain(e, "Suggestion reply parse failed"))?;
@@ -124,6 +131,14 @@ impl Action for SearchEngine {
                     .icon_parsed
                     .clone()
                     .or(Some(Icon::FontAwesome("search".into()))),
+                alternatives: vec![Alternative {
+                    name: "Copy suggestion".into(),
+                    action: Arc::new(PartialAction::new(
+                        Arc::new(CopyAction {}),
+                        a.as_str().unwrap().into(),
+                        None,
+                    )),
+                }],
                 ..Item::default()
             })
             .collect::<Vec<Item>>())
@@ -156,6 +171,7 @@ pub fn get(config: &Config) -> Vec<Item> {
                     .icon_parsed
                     .clone()
                     .or(Some(Icon::FontAwesome("search".into()))),
+                trigger: site.trigger.clone(),
                 action: Some(Arc::new(site)),
                 ..Item::default()
             }
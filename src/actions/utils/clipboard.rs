@@ -0,0 +1,170 @@
+extern crate gdk;
+extern crate gdk_pixbuf;
+extern crate glib;
+extern crate gtk;
+extern crate gtk_sys;
+
+use std::path::PathBuf;
+
+use crate::{
+    actions::utils::subprocess,
+    mcore::{
+        action::{Action, ActionResult},
+        errors::*,
+    },
+};
+
+/// Check that clipboard can be set, which requires GTK main loop to run
+/// (both for setting it and for serving its content to other applications)
+fn check_available() -> Result<()> {
+    if !gtk::is_initialized() {
+        bail!("Clipboard is not available without GTK");
+    }
+    // gtk::main_level() asserts main thread, while this is called from actions
+    if unsafe { gtk_sys::gtk_main_level() } == 0 {
+        bail!("Clipboard is not available without GTK main loop (e.g. in terminal)");
+    }
+    Ok(())
+}
+
+/// Set text to CLIPBOARD, callable from any thread
+///
+/// GTK clipboard can only be used in main thread, so the text is actually set
+/// later in main loop.
+pub fn set_text(text: &str) -> Result<()> {
+    check_available()?;
+    let text = text.to_string();
+    glib::idle_add(move || {
+        let clipboard = gtk::Clipboard::get(&gdk::Atom::intern("CLIPBOARD"));
+        clipboard.set_text(&text);
+        glib::Continue(false)
+    });
+    Ok(())
+}
+
+/// Clipboard content, with all its formats
+#[derive(Clone, Debug, Default)]
+pub struct ClipboardContent {
+    pub text: Option<String>,
+    pub html: Option<String>,
+    /// Image, stored as PNG file
+    pub image: Option<PathBuf>,
+}
+
+const TARGET_TEXT: u32 = 0;
+const TARGET_HTML: u32 = 1;
+const TARGET_IMAGE: u32 = 2;
+
+impl ClipboardContent {
+    fn target_entries(&self) -> Vec<gtk::TargetEntry> {
+        let mut targets = Vec::new();
+        let mut add = |names: &[&str], info| {
+            for name in names {
+                targets.push(gtk::TargetEntry::new(name, gtk::TargetFlags::empty(), info));
+            }
+        };
+        if self.html.is_some() {
+            add(&["text/html"], TARGET_HTML);
+        }
+        if self.text.is_some() {
+            add(
+                &[
+                    "UTF8_STRING",
+                    "text/plain;charset=utf-8",
+                    "text/plain",
+                    "STRING",
+                    "TEXT",
+                ],
+                TARGET_TEXT,
+            );
+        }
+        if self.image.is_some() {
+            add(&["image/png", "image/bmp", "image/jpeg"], TARGET_IMAGE);
+        }
+        targets
+    }
+
+    fn fill_selection(&self, selection: &gtk::SelectionData, info: u32) {
+        match info {
+            TARGET_TEXT => {
+                selection.set_text(self.text.as_deref().unwrap_or_default());
+            }
+            TARGET_HTML => selection.set(
+                &gdk::Atom::intern("text/html"),
+                8,
+                self.html.as_deref().unwrap_or_default().as_bytes(),
+            ),
+            TARGET_IMAGE => {
+                if let Some(ref path) = self.image {
+                    match gdk_pixbuf::Pixbuf::from_file(path) {
+                        Ok(pixbuf) => {
+                            selection.set_pixbuf(&pixbuf);
+                        }
+                        Err(error) => warn!("Unable to load image {:?}: {}", path, error),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Set content to CLIPBOARD (and PRIMARY if `primary`), offering all its formats,
+/// callable from any thread
+pub fn set_content(content: ClipboardContent, primary: bool) -> Result<()> {
+    check_available()?;
+    let targets = content.target_entries();
+    if targets.is_empty() {
+        bail!("Empty clipboard content");
+    }
+    glib::idle_add(move || {
+        let selections: &[&str] = if primary {
+            &["CLIPBOARD", "PRIMARY"]
+        } else {
+            &["CLIPBOARD"]
+        };
+        for selection in selections {
+            let clipboard = gtk::Clipboard::get(&gdk::Atom::intern(selection));
+            let content = content.clone();
+            if !clipboard.set_with_data(&targets, move |_, selection, info| {
+                content.fill_selection(selection, info)
+            }) {
+                warn!("Unable to set {} content", selection);
+            }
+        }
+        glib::Continue(false)
+    });
+    Ok(())
+}
+
+/// Run `command` (e.g. xdotool) to paste into the focused window, after `delay_ms`
+///
+/// The delay allows our window to be hidden and focus to return to the previous window.
+pub fn paste(command: &[String], delay_ms: u32) -> Result<()> {
+    if command.is_empty() {
+        bail!("Paste command is empty");
+    }
+    let command = command.to_vec();
+    glib::timeout_add(delay_ms, move || {
+        let args = command[1..].iter().map(|x| x.as_str()).collect::<Vec<_>>();
+        if let Err(error) = subprocess::spawn(&command[0], &args) {
+            warn!("Unable to run paste command: {}", error);
+        }
+        glib::Continue(false)
+    });
+    Ok(())
+}
+
+pub struct CopyAction {}
+
+impl Action for CopyAction {
+    fn runnable_arg(&self) -> bool {
+        true
+    }
+
+    fn run_arg(&self, text: &str) -> ActionResult {
+        info!("Copying to clipboard: {}", text);
+        set_text(text)?;
+        Ok(Vec::new())
+    }
+}
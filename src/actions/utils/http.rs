@@ -0,0 +1,52 @@
+extern crate reqwest;
+
+use std::{sync::mpsc, thread, time::Duration};
+
+use crate::mcore::{cancel, errors::*};
+
+/// Interval to check for cancellation while waiting for the reply
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Hard limit of a whole request, a blocking request can not be aborted otherwise
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HTTP client with `REQUEST_TIMEOUT`, to be used with `get_text`
+pub fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .expect("Unable to build HTTP client")
+}
+
+/// GET `url` using `client`, return the reply as text
+///
+/// The request is performed in a helper thread, so that the action returns
+/// as soon as it's cancelled. The request itself is not aborted: the helper
+/// thread keeps running until the reply is received or the client times out
+/// (see `client`), and its result is discarded.
+pub fn get_text(client: reqwest::Client, url: &str) -> Result<String> {
+    let handle = cancel::current();
+    let url = url.to_string();
+    let (send_ch, recv_ch) = mpsc::channel::<Result<String>>();
+    thread::Builder::new().name("http".into()).spawn(move || {
+        let res = client
+            .get(&url)
+            .send()
+            .map_err(|e| Error::with_chain(e, "Failed to perform HTTP request"))
+            .and_then(|mut reply| {
+                reply
+                    .text()
+                    .map_err(|e| Error::with_chain(e, "Failed to read request reply"))
+            });
+        // receiver may be gone if cancelled
+        let _ = send_ch.send(res);
+    })?;
+
+    loop {
+        handle.check()?;
+        match recv_ch.recv_timeout(POLL_INTERVAL) {
+            Ok(res) => return res,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => bail!("HTTP request thread died"),
+        }
+    }
+}
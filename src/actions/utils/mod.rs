@@ -3,5 +3,8 @@
 // @Last Modified by:   BlahGeek
 // @Last Modified time: 2017-08-19
 
+pub mod clipboard;
+pub mod http;
 pub mod open;
 pub mod subprocess;
+pub mod window;
@@ -0,0 +1,152 @@
+// Query X11 windows, e.g. to know which application owns the clipboard
+//
+// A short-lived X connection is opened for each query. Errors (e.g. the owner
+// window is destroyed while being queried) are ignored, so this is only safe to
+// call from the main thread, where no other X error handler is changed.
+
+extern crate x11;
+
+use self::x11::xlib;
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_int,
+    ptr,
+};
+
+unsafe extern "C" fn ignore_x_error(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> c_int {
+    0
+}
+
+/// Owner window of the CLIPBOARD selection
+unsafe fn clipboard_owner(display: *mut xlib::Display) -> Option<xlib::Window> {
+    let name = CString::new("CLIPBOARD").unwrap();
+    let atom = xlib::XInternAtom(display, name.as_ptr(), xlib::False);
+    match xlib::XGetSelectionOwner(display, atom) {
+        0 => None,
+        window => Some(window),
+    }
+}
+
+/// Active window by EWMH `_NET_ACTIVE_WINDOW`, falling back to input focus
+unsafe fn active_window(display: *mut xlib::Display) -> Option<xlib::Window> {
+    let root = xlib::XDefaultRootWindow(display);
+    let name = CString::new("_NET_ACTIVE_WINDOW").unwrap();
+    let atom = xlib::XInternAtom(display, name.as_ptr(), xlib::False);
+
+    let mut actual_type = 0;
+    let mut actual_format: c_int = 0;
+    let mut nitems = 0;
+    let mut bytes_after = 0;
+    let mut prop = ptr::null_mut();
+    let status = xlib::XGetWindowProperty(
+        display,
+        root,
+        atom,
+        0,
+        1,
+        xlib::False,
+        xlib::XA_WINDOW,
+        &mut actual_type,
+        &mut actual_format,
+        &mut nitems,
+        &mut bytes_after,
+        &mut prop,
+    );
+    let mut window = None;
+    if !prop.is_null() {
+        if status == xlib::Success as c_int && nitems == 1 && actual_format == 32 {
+            window = Some(*(prop as *const xlib::Window));
+        }
+        xlib::XFree(prop as *mut _);
+    }
+    window.filter(|&x| x != 0).or_else(|| {
+        let mut focus = 0;
+        let mut revert_to = 0;
+        xlib::XGetInputFocus(display, &mut focus, &mut revert_to);
+        if focus == 0 || focus == xlib::PointerRoot as xlib::Window {
+            None
+        } else {
+            Some(focus)
+        }
+    })
+}
+
+/// Class of `window` (second part of WM_CLASS), e.g. "Firefox"
+unsafe fn window_class(display: *mut xlib::Display, window: xlib::Window) -> Option<String> {
+    let mut hint = xlib::XClassHint {
+        res_name: ptr::null_mut(),
+        res_class: ptr::null_mut(),
+    };
+    if xlib::XGetClassHint(display, window, &mut hint) == 0 {
+        return None;
+    }
+    let class = if hint.res_class.is_null() {
+        None
+    } else {
+        Some(
+            CStr::from_ptr(hint.res_class)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    };
+    if !hint.res_name.is_null() {
+        xlib::XFree(hint.res_name as *mut _);
+    }
+    if !hint.res_class.is_null() {
+        xlib::XFree(hint.res_class as *mut _);
+    }
+    class
+}
+
+/// Class of `window` or its closest ancestor with one, as selections are
+/// often owned by child windows without WM_CLASS
+unsafe fn client_class(display: *mut xlib::Display, window: xlib::Window) -> Option<String> {
+    let mut window = window;
+    loop {
+        if let Some(class) = window_class(display, window) {
+            return Some(class);
+        }
+        let mut root = 0;
+        let mut parent = 0;
+        let mut children = ptr::null_mut();
+        let mut nchildren = 0;
+        if xlib::XQueryTree(
+            display,
+            window,
+            &mut root,
+            &mut parent,
+            &mut children,
+            &mut nchildren,
+        ) == 0
+        {
+            return None;
+        }
+        if !children.is_null() {
+            xlib::XFree(children as *mut _);
+        }
+        if parent == 0 || parent == root {
+            return None;
+        }
+        window = parent;
+    }
+}
+
+/// Window class of the application owning the clipboard, or of the active
+/// window if the owner has none. None if unavailable (e.g. not running in X11)
+pub fn clipboard_owner_class() -> Option<String> {
+    unsafe {
+        let display = xlib::XOpenDisplay(ptr::null());
+        if display.is_null() {
+            return None;
+        }
+        let previous_handler = xlib::XSetErrorHandler(Some(ignore_x_error));
+        let class = clipboard_owner(display)
+            .and_then(|window| client_class(display, window))
+            .or_else(|| active_window(display).and_then(|window| client_class(display, window)));
+        xlib::XSync(display, xlib::False);
+        xlib::XSetErrorHandler(previous_handler);
+        xlib::XCloseDisplay(display);
+        class
+    }
+}
@@ -11,7 +11,7 @@ use crate::mcore::{
 };
 use std::sync::Arc;
 
-use crate::actions::utils::open::OpenAction;
+use crate::actions::utils::{http, open::OpenAction};
 
 struct WolframAlpha {
     appid: String,
@@ -28,9 +28,7 @@ impl Action for WolframAlpha {
     fn run_arg(&self, text: &str) -> ActionResult {
         let url = Url::parse_with_params(API_URL, &[("appid", self.appid.as_str()), ("i", text)])
             .unwrap();
-        let response = reqwest::get(url)
-            .map_err(|e| Error::with_chain(e, "Failed to send API request"))?
-            .text()
+        let response = http::get_text(http::client(), url.as_str())
             .map_err(|e| Error::with_chain(e, "Failed to get API reply"))?;
         Ok(vec![Item {
             title: response,
@@ -40,6 +38,9 @@ impl Action for WolframAlpha {
             priority: 0,
             data: None,
             search_str: None,
+            keywords: Vec::new(),
+            trigger: None,
+            alternatives: Vec::new(),
             action: Some(Arc::new(PartialAction::new(
                 Arc::new(OpenAction {}),
                 Url::parse_with_params(SEARCH_URL, &[("i", text)])
@@ -60,6 +61,9 @@ pub fn get(config: &Config) -> Item {
         priority: 0,
         data: None,
         search_str: None,
+        keywords: Vec::new(),
+        trigger: None,
+        alternatives: Vec::new(),
         action: Some(Arc::new(WolframAlpha {
             appid: config.get::<String>(&["wolframalpha", "appid"]).unwrap(),
         })),
@@ -10,13 +10,16 @@ extern crate url;
 
 use self::{crypto::digest::Digest, url::form_urlencoded};
 
-use crate::mcore::{
-    action::{Action, ActionResult},
-    config::Config,
-    errors::*,
-    item::{Icon, Item},
+use crate::{
+    actions::utils::http,
+    mcore::{
+        action::{Action, ActionResult},
+        config::Config,
+        errors::*,
+        item::{Icon, Item},
+    },
 };
-use std::{io::Read, sync::Arc};
+use std::sync::Arc;
 
 struct Youdao {}
 
@@ -64,11 +67,7 @@ impl Action for Youdao {
         let url = String::new() + "https://openapi.youdao.com/api?" + &encoded;
         trace!("Youdao request url: {}", url);
 
-        let mut result = String::new();
-        reqwest::get(&url)
-            .map_err(|e| Error::with_chain(e, "Failed to perform HTTP request"))?
-            .read_to_string(&mut result)
-            .map_err(|e| Error::with_chain(e, "Failed to read request reply"))?;
+        let result = http::get_text(http::client(), &url)?;
 
         let result: YoudaoResult = serde_json::from_str(&result)
             .map_err(|e| Error::with_chain(e, "Failed parsing JSON"))?;
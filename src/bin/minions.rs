@@ -3,9 +3,122 @@
 // @Last Modified by:   BlahGeek
 // @Last Modified time: 2020-01-17
 
+use chrono::TimeZone;
 use dirs;
-use minions::frontend::app::MinionsApp;
-use std::path::Path;
+use error_chain::ChainedError;
+use minions::{
+    frontend::{
+        app::MinionsApp,
+        control::{self, Command},
+        tui::TuiApp,
+    },
+    mcore::{config::Config, errors::*, matcher::StatisticsFile},
+};
+use std::{io::Read, path::Path};
+
+/// Get quicksend argument from command line:
+/// None for not quicksending, Some(None) for quicksending from clipboard
+fn get_quicksend_arg(args: &clap::ArgMatches) -> Result<Option<Option<String>>> {
+    if args.is_present("quicksend") {
+        return Ok(Some(None));
+    }
+    if let Some(text) = args.value_of("send") {
+        if text != "-" {
+            return Ok(Some(Some(text.into())));
+        }
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .chain_err(|| "Unable to read text from stdin")?;
+        if text.ends_with('\n') {
+            text.pop();
+        }
+        return Ok(Some(Some(text)));
+    }
+    if let Some(paths) = args.values_of("send-file") {
+        let paths = paths
+            .map(|path| {
+                Path::new(path)
+                    .canonicalize()
+                    .map(|x| x.to_string_lossy().into_owned())
+                    .chain_err(|| format!("Invalid file path {:?}", path))
+            })
+            .collect::<Result<Vec<String>>>()?;
+        return Ok(Some(Some(paths.join("\n"))));
+    }
+    Ok(None)
+}
+
+/// Run `minions stats` subcommand
+fn run_stats(config: &Config, args: &clap::ArgMatches) -> Result<()> {
+    let global_config = config.partial(&["core"]).unwrap();
+    let mut stats =
+        StatisticsFile::from_config(&global_config).chain_err(|| "Unable to open statistics")?;
+    match args.subcommand() {
+        ("compact", _) => {
+            println!("Dropped {} entries", stats.compact());
+        }
+        ("reset", _) => {
+            stats.reset();
+            println!("Dropped all entries");
+        }
+        ("forget", Some(args)) => {
+            let title = args.value_of("title").unwrap();
+            let patterns = args
+                .values_of("pattern")
+                .map(|x| x.collect())
+                .unwrap_or_else(Vec::new);
+            println!("Dropped {} entries", stats.forget(title, &patterns));
+        }
+        ("export", Some(args)) => {
+            let path = Path::new(args.value_of("path").unwrap());
+            stats
+                .export(path)
+                .chain_err(|| format!("Unable to export to {:?}", path))?;
+            println!("Exported to {:?}", path);
+            return Ok(());
+        }
+        ("import", Some(args)) => {
+            let path = Path::new(args.value_of("path").unwrap());
+            stats
+                .import(path)
+                .chain_err(|| format!("Unable to import {:?}", path))?;
+            println!("Imported {} entries", stats.summary().entries);
+        }
+        ("merge", Some(args)) => {
+            let path = Path::new(args.value_of("path").unwrap());
+            let count = stats
+                .merge(path)
+                .chain_err(|| format!("Unable to merge {:?}", path))?;
+            println!("Merged {} entries", count);
+        }
+        _ => {
+            let summary = stats.summary();
+            println!("File: {}", stats.path().display());
+            println!("Entries: {} (legacy: {})", summary.entries, summary.legacy);
+            println!("Decayed entries: {}", summary.decayed);
+            println!("Total weight: {:.2}", summary.total_weight);
+            if let Some(time) = summary
+                .last_time
+                .and_then(|time| chrono::Local.timestamp_opt(time, 0).single())
+            {
+                println!("Last hit: {}", time.format("%Y-%m-%d %H:%M:%S"));
+            }
+            if !summary.salt_matched {
+                println!("Salt changed, entries would be dropped on next start");
+            }
+            return Ok(());
+        }
+    }
+    stats.save().chain_err(|| "Unable to save statistics")?;
+
+    // the running instance keeps statistics in memory
+    let control_socket = config.get_filename(&["core", "control_socket"]).unwrap();
+    if control::is_alive(&control_socket) {
+        control::send(&control_socket, &Command::Reload)?;
+    }
+    Ok(())
+}
 
 fn main() {
     let mut logger = fern::Dispatch::new().level(log::LevelFilter::Warn);
@@ -26,9 +139,115 @@ fn main() {
                 .multiple(true)
                 .help("Increase logging verbosity, up to 2 times"),
         )
+        .arg(
+            clap::Arg::with_name("daemon")
+                .short("d")
+                .long("daemon")
+                .help("Keep running in background, controlled by `minions ctl`"),
+        )
+        .arg(
+            clap::Arg::with_name("quicksend")
+                .long("quicksend")
+                .conflicts_with_all(&["daemon", "send", "send-file"])
+                .help("Start with selected text (or clipboard content) sent"),
+        )
+        .arg(
+            clap::Arg::with_name("send")
+                .long("send")
+                .value_name("TEXT")
+                .takes_value(true)
+                .conflicts_with_all(&["daemon", "send-file"])
+                .help("Start with TEXT sent, read from stdin if TEXT is \"-\""),
+        )
+        .arg(
+            clap::Arg::with_name("send-file")
+                .long("send-file")
+                .value_name("PATH")
+                .takes_value(true)
+                .multiple(true)
+                .conflicts_with("daemon")
+                .help("Start with file paths sent (one per line)"),
+        )
+        .arg(
+            clap::Arg::with_name("tui")
+                .long("tui")
+                .conflicts_with_all(&["daemon", "quicksend", "send", "send-file"])
+                .help("Run in terminal instead of GUI"),
+        )
+        .arg(
+            clap::Arg::with_name("dmenu")
+                .long("dmenu")
+                .conflicts_with_all(&["daemon", "tui", "quicksend", "send", "send-file"])
+                .help("Run in terminal like dmenu: choose one line from stdin, print it to stdout"),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("ctl")
+                .about("Send command to running Minions")
+                .arg(
+                    clap::Arg::with_name("command")
+                        .required(true)
+                        .possible_values(&["show", "quicksend", "reload", "quit"])
+                        .help("Command to send"),
+                )
+                .arg(
+                    clap::Arg::with_name("text")
+                        .help("Text to quicksend, default to clipboard content"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("stats")
+                .about("Manage statistics of selected items (show summary by default)")
+                .subcommand(clap::SubCommand::with_name("show").about("Show summary"))
+                .subcommand(
+                    clap::SubCommand::with_name("compact")
+                        .about("Drop decayed entries, also done on start"),
+                )
+                .subcommand(clap::SubCommand::with_name("reset").about("Drop all entries"))
+                .subcommand(
+                    clap::SubCommand::with_name("forget")
+                        .about("Drop entries of an item")
+                        .after_help(
+                            "Entries with substrings of the title or initials of its words as \
+                             filter text are dropped too, other filter text must be given by \
+                             --pattern",
+                        )
+                        .arg(
+                            clap::Arg::with_name("title")
+                                .required(true)
+                                .help("Exact title of the item"),
+                        )
+                        .arg(
+                            clap::Arg::with_name("pattern")
+                                .long("pattern")
+                                .takes_value(true)
+                                .multiple(true)
+                                .help(
+                                    "Filter text used to select the item, besides the guessed ones",
+                                ),
+                        ),
+                )
+                .subcommand(
+                    clap::SubCommand::with_name("export")
+                        .about("Write statistics to file (hashes only)")
+                        .arg(clap::Arg::with_name("path").required(true)),
+                )
+                .subcommand(
+                    clap::SubCommand::with_name("import")
+                        .about("Replace statistics with file exported with the same salt")
+                        .arg(clap::Arg::with_name("path").required(true)),
+                )
+                .subcommand(
+                    clap::SubCommand::with_name("merge")
+                        .about("Add statistics from file exported with the same salt")
+                        .arg(clap::Arg::with_name("path").required(true)),
+                ),
+        )
         .get_matches();
 
+    let in_terminal = args.is_present("tui") || args.is_present("dmenu");
     logger = match args.occurrences_of("verbose") {
+        // do not mess up the terminal UI
+        0 if in_terminal => logger.level_for("minions", log::LevelFilter::Error),
         0 => logger.level_for("minions", log::LevelFilter::Info),
         1 => logger.level_for("minions", log::LevelFilter::Debug),
         _ => logger.level_for("minions", log::LevelFilter::Trace),
@@ -55,7 +274,80 @@ fn main() {
             .join(".local/share/minions/config.toml"),
     };
 
+    if let Some(args) = args.subcommand_matches("ctl") {
+        let config = Config::new(&configfile);
+        let control_socket = config.get_filename(&["core", "control_socket"]).unwrap();
+        let command = match args.value_of("command").unwrap() {
+            "show" => Command::Show,
+            "quicksend" => Command::Quicksend(args.value_of("text").map(|x| x.into())),
+            "reload" => Command::Reload,
+            _ => Command::Quit,
+        };
+        if let Err(error) = control::send(&control_socket, &command) {
+            eprintln!("{}", error.display_chain());
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(args) = args.subcommand_matches("stats") {
+        let config = Config::new(&configfile);
+        if let Err(error) = run_stats(&config, args) {
+            eprintln!("{}", error.display_chain());
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if in_terminal {
+        let config = Config::new(&configfile);
+        let res = if args.is_present("dmenu") {
+            let mut lines = String::new();
+            std::io::stdin()
+                .read_to_string(&mut lines)
+                .chain_err(|| "Unable to read lines from stdin")
+                .and_then(|_| TuiApp::run_dmenu(&config, lines.lines().map(|x| x.into()).collect()))
+        } else {
+            // GTK is optional here, used for clipboard only
+            if gtk::init().is_err() {
+                log::info!("GTK unavailable, clipboard is disabled");
+            }
+            TuiApp::run_actions(&configfile)
+        };
+        match res {
+            Ok(code) => std::process::exit(code),
+            Err(error) => {
+                eprintln!("{}", error.display_chain());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let quicksend = match get_quicksend_arg(&args) {
+        Ok(quicksend) => quicksend,
+        Err(error) => {
+            eprintln!("{}", error.display_chain());
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(ref text) = quicksend {
+        // hand over to running instance if any
+        let config = Config::new(&configfile);
+        let control_socket = config.get_filename(&["core", "control_socket"]).unwrap();
+        if control::is_alive(&control_socket) {
+            if let Err(error) = control::send(&control_socket, &Command::Quicksend(text.clone())) {
+                eprintln!("{}", error.display_chain());
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+
     gtk::init().expect("Failed to initialize GTK");
-    let _ = MinionsApp::new(&configfile);
+    let app = MinionsApp::new(&configfile, args.is_present("daemon"));
+    if let Some(text) = quicksend {
+        app.with(|app| app.borrow_mut().as_mut().unwrap().show_quicksend(text));
+    }
     gtk::main();
 }
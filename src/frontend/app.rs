@@ -6,13 +6,18 @@
 use crate::frontend::{gdk, gtk, gtk::prelude::*};
 use std::{self};
 
-use std::{cell::RefCell, ops::Deref, rc::Rc, sync::mpsc, thread};
+use std::{cell::RefCell, io, ops::Deref, rc::Rc, sync::mpsc, thread};
 
 use crate::{
-    frontend::ui::MinionsUI,
+    frontend::{control, control::Command, hotkey, hotkey::Hotkey, ui::MinionsUI},
     mcore::{
-        action::ActionResult, config::Config, context::Context, errors::Error, item::Item,
-        matcher::Matcher,
+        action::{ActionEvent, ActionResult},
+        cancel::CancelHandle,
+        config::Config,
+        context::Context,
+        errors::{Error, ErrorKind},
+        item::Item,
+        matcher::{Matcher, SortTask},
     },
 };
 
@@ -22,7 +27,8 @@ use glib::translate::FromGlib;
 #[derive(Clone)]
 enum Status {
     Initial,
-    Running(Rc<mpsc::Receiver<ActionResult>>),
+    /// receiver, cancel handle, and whether any batch is received
+    Running(Rc<mpsc::Receiver<ActionEvent>>, CancelHandle, bool),
     Error(Rc<Error>), // Rc is for Clone
     Default,
     Filtering {
@@ -35,8 +41,8 @@ enum Status {
         item: Rc<Item>, // entering text for item
         suggestions: Vec<Rc<Item>>,
         selected_idx: i32, // selected index of suggestions
-        receiver: Option<Rc<mpsc::Receiver<ActionResult>>>, /* receiver for
-                            * running suggestion */
+        // receiver for running suggestion
+        receiver: Option<(Rc<mpsc::Receiver<ActionResult>>, CancelHandle)>,
     },
 }
 
@@ -47,12 +53,55 @@ pub struct MinionsApp {
     status: Status,
     filter_timeout: std::time::Duration,
     matcher: Matcher,
+    /// Receiver and cancel handle for filtering in background
+    filtering: Option<(mpsc::Receiver<FilterResult>, CancelHandle)>,
+
+    configpath: std::path::PathBuf,
+    /// Keep running in background (hide window instead of quitting)
+    daemon: bool,
 }
 
+/// Lists with more items are filtered in background
+const ASYNC_FILTER_MIN_ITEMS: usize = 2000;
+
+type FilterResult = (SortTask, Result<Vec<usize>, Error>);
+
 thread_local! {
     static APP: RefCell<Option<MinionsApp>> = RefCell::new(None);
 }
 
+/// Callback for running action, forward events to main loop
+fn running_callback(send_ch: mpsc::Sender<ActionEvent>) -> impl FnMut(ActionEvent) + Send {
+    move |event| {
+        if let Err(error) = send_ch.send(event) {
+            debug!("Unable to send to channel: {}", error);
+        } else {
+            glib::idle_add(|| {
+                APP.with(move |app| {
+                    app.borrow_mut()
+                        .as_mut()
+                        .unwrap()
+                        .process_running_callback()
+                });
+                Continue(false)
+            });
+        }
+    }
+}
+
+/// Run command in main loop, callable from any thread
+fn dispatch_command(command: Command) {
+    glib::idle_add(move || {
+        let command = command.clone();
+        APP.with(|app| {
+            if let Some(ref mut app) = *app.borrow_mut() {
+                app.process_command(command)
+            }
+        });
+        Continue(false)
+    });
+}
+
 impl MinionsApp {
     fn update_ui(&self) {
         trace!("update ui");
@@ -65,12 +114,20 @@ impl MinionsApp {
                 self.ui.set_items(Vec::new(), -1, &self.ctx);
                 self.ui.set_spinning(false);
             }
-            Status::Running(_) => {
+            Status::Running(_, _, streamed) => {
                 self.ui.set_entry(None);
                 self.ui.set_filter_text("");
                 self.ui.set_action(None);
                 self.ui.set_reference(None);
-                self.ui.set_items(Vec::new(), -1, &self.ctx);
+                if streamed {
+                    self.ui.set_items(
+                        self.ctx.list_items.iter().map(|x| x.deref()).collect(),
+                        -1,
+                        &self.ctx,
+                    );
+                } else {
+                    self.ui.set_items(Vec::new(), -1, &self.ctx);
+                }
                 self.ui.set_spinning(true);
             }
             Status::Error(ref error) => {
@@ -90,7 +147,7 @@ impl MinionsApp {
                 self.ui.set_reference(self.ctx.reference.as_ref());
                 if self.ctx.list_items.len() == 0 {
                     debug!("No more listing items!");
-                    gtk::main_quit();
+                    self.dismiss();
                 }
                 self.ui.set_items(
                     self.ctx.list_items.iter().map(|x| x.deref()).collect(),
@@ -154,17 +211,19 @@ impl MinionsApp {
 
     fn process_keyevent_escape(&mut self) {
         trace!("Processing keyevent Escape");
+        self.cancel_running();
         self.status = match self.status {
             Status::Initial => {
                 debug!("Quit!");
+                self.dismiss();
                 Status::Initial
             }
             Status::Default => {
                 self.ctx.reset();
                 Status::Initial
             }
-            Status::Running(_) => {
-                debug!("Drop thread");
+            Status::Running(..) => {
+                debug!("Cancel running action");
                 Status::Default
             }
             _ => Status::Default,
@@ -211,6 +270,7 @@ impl MinionsApp {
                 selected_idx,
                 ..
             } => {
+                self.cancel_running();
                 let mut new_idx = selected_idx + delta;
                 if new_idx >= suggestions.len() as i32 {
                     new_idx = suggestions.len() as i32 - 1;
@@ -239,18 +299,37 @@ impl MinionsApp {
                 ref filtered_items,
                 ..
             } => {
-                if selected_idx < 0 || self.ctx.reference.is_some() {
-                    debug!("No item to send or not sendable");
+                if self.ctx.reference.is_some() {
+                    debug!("Not sendable");
+                    self.status.clone()
+                } else if !self.ctx.marked_items.is_empty() {
+                    if let Err(error) = self.ctx.quicksend_marked() {
+                        debug!(
+                            "Unable to quicksend marked items: {}",
+                            error.display_chain()
+                        );
+                        Status::Error(Rc::new(error))
+                    } else {
+                        self.rank_for_reference();
+                        Status::Default
+                    }
+                } else if selected_idx < 0 {
+                    debug!("No item to send");
                     self.status.clone()
                 } else {
                     let item = &filtered_items[selected_idx as usize];
-                    if let Err(error) = self.matcher.record(Some(&filter_text), &item) {
+                    if let Err(error) = self.matcher.record(
+                        Some(&filter_text),
+                        self.ctx.reference.as_deref(),
+                        &item,
+                    ) {
                         warn!("Unable to record hit: {}", error);
                     }
                     if let Err(error) = self.ctx.quicksend(item) {
                         debug!("Unable to quicksend item: {}", error.display_chain());
                         Status::Error(Rc::new(error))
                     } else {
+                        self.rank_for_reference();
                         Status::Default
                     }
                 }
@@ -287,15 +366,100 @@ impl MinionsApp {
         };
 
         if let Some(newfilter) = newfilter {
-            let filtered_items = self.matcher.sort(&newfilter, &self.ctx.list_items);
-            let selected_idx = if filtered_items.len() == 0 { -1 } else { 0 };
+            self.filter(newfilter);
+        }
+    }
 
-            self.status = Status::Filtering {
-                selected_idx,
-                filter_text: newfilter,
-                filtered_items,
-                timestamp: std::time::Instant::now(),
-            };
+    /// Filter items with `filter_text`, large lists are filtered in
+    /// background and previous results are shown until done
+    fn filter(&mut self, filter_text: String) {
+        if let Some((_, handle)) = self.filtering.take() {
+            handle.cancel();
+        }
+        let task = self
+            .matcher
+            .sort_task(&filter_text, &self.ctx.list_items, self.ctx.list_scorer)
+            .with_reference(self.ctx.reference.as_deref());
+        let filtered_items = if task.len() < ASYNC_FILTER_MIN_ITEMS {
+            task.run()
+                .ok()
+                .and_then(|indices| self.matcher.resolve(&task, &indices))
+                .unwrap_or_default()
+        } else {
+            let handle = CancelHandle::new();
+            let task = task.with_cancel(handle.clone());
+            let (send_ch, recv_ch) = mpsc::channel::<FilterResult>();
+            thread::spawn(move || {
+                let res = task.run();
+                if send_ch.send((task, res)).is_ok() {
+                    glib::idle_add(|| {
+                        APP.with(|app| {
+                            if let Some(ref mut app) = *app.borrow_mut() {
+                                app.process_filter_callback()
+                            }
+                        });
+                        Continue(false)
+                    });
+                }
+            });
+            self.filtering = Some((recv_ch, handle));
+            match self.status {
+                Status::Filtering {
+                    ref filtered_items, ..
+                } => filtered_items.clone(),
+                _ => self.ctx.list_items.clone(),
+            }
+        };
+        let selected_idx = if filtered_items.is_empty() { -1 } else { 0 };
+
+        self.status = Status::Filtering {
+            selected_idx,
+            filter_text,
+            filtered_items,
+            timestamp: std::time::Instant::now(),
+        };
+        self.update_ui();
+    }
+
+    fn process_filter_callback(&mut self) {
+        let (task, res) = match self.filtering {
+            Some((ref recv_ch, _)) => match recv_ch.try_recv() {
+                Ok(result) => result,
+                Err(_) => {
+                    debug!("Unable to receive filter result from channel");
+                    return;
+                }
+            },
+            None => return,
+        };
+        self.filtering = None;
+        let indices = match res {
+            Ok(indices) => indices,
+            Err(error) => {
+                debug!("Filtering stopped: {}", error);
+                return;
+            }
+        };
+        if let Status::Filtering {
+            ref filter_text,
+            ref mut filtered_items,
+            ref mut selected_idx,
+            ..
+        } = self.status
+        {
+            if filter_text != task.pattern() {
+                return;
+            }
+            match self.matcher.resolve(&task, &indices) {
+                Some(items) => {
+                    *selected_idx = if items.is_empty() { -1 } else { 0 };
+                    *filtered_items = items;
+                }
+                None => {
+                    debug!("Items changed while filtering");
+                    return;
+                }
+            }
             self.update_ui();
         }
     }
@@ -310,12 +474,21 @@ impl MinionsApp {
                 ref filtered_items,
                 ..
             } => {
-                if selected_idx < 0 {
-                    debug!("No item to select");
-                    self.status.clone()
-                } else {
-                    let item = &filtered_items[selected_idx as usize];
-                    if let Err(error) = self.matcher.record(Some(&filter_text), &item) {
+                // filter text as trigger keyword takes precedence over the
+                // selected item
+                let item = self.ctx.find_trigger(filter_text).or_else(|| {
+                    if selected_idx < 0 {
+                        None
+                    } else {
+                        Some(filtered_items[selected_idx as usize].clone())
+                    }
+                });
+                if let Some(ref item) = item {
+                    if let Err(error) = self.matcher.record(
+                        Some(&filter_text),
+                        self.ctx.reference.as_deref(),
+                        &item,
+                    ) {
                         warn!("Unable to record hit: {}", error);
                     }
                     if self.ctx.selectable_with_text(item) {
@@ -336,6 +509,9 @@ impl MinionsApp {
                         debug!("Item not selectable with text");
                         self.status.clone()
                     }
+                } else {
+                    debug!("No item to select");
+                    self.status.clone()
                 }
             }
             status @ _ => status,
@@ -361,7 +537,7 @@ impl MinionsApp {
             if entry_text.len() > 0 && self.ctx.runnable_with_text_realtime(&item) {
                 let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
                 let entry_text_ = entry_text.to_owned();
-                self.ctx.async_run_with_text_realtime(
+                let handle = self.ctx.async_run_with_text_realtime(
                     &item,
                     &entry_text,
                     move |res: ActionResult| {
@@ -384,7 +560,7 @@ impl MinionsApp {
                     item,
                     suggestions,
                     selected_idx: -1,
-                    receiver: Some(Rc::new(recv_ch)),
+                    receiver: Some((Rc::new(recv_ch), handle)),
                 };
             }
         }
@@ -394,7 +570,7 @@ impl MinionsApp {
         if let Status::Entering {
             item,
             suggestions,
-            receiver: Some(receiver),
+            receiver: Some((receiver, _)),
             ..
         } = self.status.clone()
         {
@@ -408,7 +584,11 @@ impl MinionsApp {
                         receiver: None,
                     },
                     Err(error) => {
-                        warn!("Error running realtime text: {}", error.display_chain());
+                        if let ErrorKind::Timeout(_) = error.kind() {
+                            debug!("Realtime text timed out: {}", error);
+                        } else {
+                            warn!("Error running realtime text: {}", error.display_chain());
+                        }
                         Status::Entering {
                             item,
                             suggestions,
@@ -431,30 +611,44 @@ impl MinionsApp {
     }
 
     fn process_running_callback(&mut self) {
-        let mut res: Option<ActionResult> = None;
-        if let Status::Running(ref recv_ch) = self.status {
-            if let Ok(res_) = recv_ch.try_recv() {
-                trace!("Received result on callback");
-                res = Some(res_);
+        let mut event: Option<ActionEvent> = None;
+        if let Status::Running(ref recv_ch, ..) = self.status {
+            if let Ok(event_) = recv_ch.try_recv() {
+                trace!("Received event on callback");
+                event = Some(event_);
             } else {
                 debug!("Unable to receive from channel");
             }
         }
 
-        if let Some(res) = res {
-            self.status = match res {
-                Ok(res) => {
-                    self.ctx.async_select_callback(res);
-                    Status::Default
-                }
-                Err(error) => {
-                    debug!("Error from channel: {}", error.display_chain());
-                    Status::Error(Rc::new(error))
+        match event {
+            Some(ActionEvent::Batch(items)) => {
+                if let Status::Running(_, _, ref mut streamed) = self.status {
+                    trace!("Received batch of {} items", items.len());
+                    if *streamed {
+                        self.ctx.async_select_batch(items);
+                    } else {
+                        // replace the list on first batch
+                        self.ctx.async_select_callback(items);
+                        *streamed = true;
+                    }
                 }
-            };
-            self.update_ui();
-        } else {
-            debug!("No action result");
+                self.update_ui();
+            }
+            Some(ActionEvent::Done(res)) => {
+                self.status = match res {
+                    Ok(res) => {
+                        self.ctx.async_select_callback(res);
+                        Status::Default
+                    }
+                    Err(error) => {
+                        debug!("Error from channel: {}", error.display_chain());
+                        Status::Error(Rc::new(error))
+                    }
+                };
+                self.update_ui();
+            }
+            None => debug!("No action result"),
         }
     }
 
@@ -462,6 +656,43 @@ impl MinionsApp {
         trace!("Processing keyevent Enter");
         self.status = match self.status.clone() {
             status @ Status::Initial | status @ Status::Default => status,
+            Status::Filtering {
+                ref filter_text, ..
+            } if self.ctx.selectable_marked() => {
+                for item in self.ctx.marked_items.iter() {
+                    if !self.ctx.selectable(item) {
+                        continue;
+                    }
+                    if let Err(error) =
+                        self.matcher
+                            .record(Some(&filter_text), self.ctx.reference.as_deref(), item)
+                    {
+                        debug!("Unable to record hit: {}", error);
+                    }
+                }
+                let (send_ch, recv_ch) = mpsc::channel::<ActionEvent>();
+                let handle = self.ctx.async_select_marked(running_callback(send_ch));
+                Status::Running(Rc::new(recv_ch), handle, false)
+            }
+            Status::Filtering {
+                ref filter_text, ..
+            } if self.ctx.split_trigger(filter_text).is_some() => {
+                // "keyword argument" runs the triggered item with argument
+                let (keyword, item, arg) = self.ctx.split_trigger(filter_text).unwrap();
+                if let Err(error) =
+                    self.matcher
+                        .record(Some(keyword), self.ctx.reference.as_deref(), &item)
+                {
+                    debug!("Unable to record hit: {}", error);
+                }
+                let (send_ch, recv_ch) = mpsc::channel::<ActionEvent>();
+                let handle = self.ctx.async_select_with_text_streaming(
+                    &item,
+                    arg,
+                    running_callback(send_ch),
+                );
+                Status::Running(Rc::new(recv_ch), handle, false)
+            }
             Status::Filtering {
                 selected_idx,
                 ref filter_text,
@@ -473,45 +704,14 @@ impl MinionsApp {
                     self.status.clone()
                 } else {
                     let item = &filtered_items[selected_idx as usize];
-                    if let Err(error) = self.matcher.record(Some(&filter_text), &item) {
+                    if let Err(error) = self.matcher.record(
+                        Some(&filter_text),
+                        self.ctx.reference.as_deref(),
+                        &item,
+                    ) {
                         debug!("Unable to record hit: {}", error);
                     }
-
-                    let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
-                    if self.ctx.selectable(item) {
-                        self.ctx.async_select(item, move |res: ActionResult| {
-                            if let Err(error) = send_ch.send(res) {
-                                debug!("Unable to send to channel: {}", error);
-                            } else {
-                                glib::idle_add(|| {
-                                    APP.with(move |app| {
-                                        app.borrow_mut()
-                                            .as_mut()
-                                            .unwrap()
-                                            .process_running_callback()
-                                    });
-                                    Continue(false)
-                                });
-                            }
-                        });
-                        Status::Running(Rc::new(recv_ch))
-                    } else if self.ctx.selectable_with_text(&item) {
-                        Status::Entering {
-                            item: item.clone(),
-                            suggestions: self
-                                .ctx
-                                .suggest_arg(item)
-                                .unwrap_or(Vec::new())
-                                .into_iter()
-                                .map(|x| Rc::new(x))
-                                .collect(),
-                            selected_idx: -1,
-                            receiver: None,
-                        }
-                    } else {
-                        debug!("Item not selectable with or without text");
-                        self.status.clone()
-                    }
+                    self.select_item(item)
                 }
             }
             Status::Entering {
@@ -520,47 +720,24 @@ impl MinionsApp {
                 selected_idx,
                 ..
             } => {
-                let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
+                let (send_ch, recv_ch) = mpsc::channel::<ActionEvent>();
 
                 if selected_idx < 0 {
                     let text = self.ui.get_entry_text();
-                    self.ctx
-                        .async_select_with_text(&item, &text, move |res: ActionResult| {
-                            if let Err(error) = send_ch.send(res) {
-                                debug!("Unable to send to channel: {}", error);
-                            } else {
-                                glib::idle_add(|| {
-                                    APP.with(move |app| {
-                                        app.borrow_mut()
-                                            .as_mut()
-                                            .unwrap()
-                                            .process_running_callback()
-                                    });
-                                    Continue(false)
-                                });
-                            }
-                        });
-                    Status::Running(Rc::new(recv_ch))
+                    let handle = self.ctx.async_select_with_text_streaming(
+                        &item,
+                        &text,
+                        running_callback(send_ch),
+                    );
+                    Status::Running(Rc::new(recv_ch), handle, false)
                 } else {
                     let item = &suggestions[selected_idx as usize];
                     if self.ctx.selectable(&item) {
-                        let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
-                        self.ctx.async_select(&item, move |res: ActionResult| {
-                            if let Err(error) = send_ch.send(res) {
-                                debug!("Unable to send to channel: {}", error);
-                            } else {
-                                glib::idle_add(|| {
-                                    APP.with(move |app| {
-                                        app.borrow_mut()
-                                            .as_mut()
-                                            .unwrap()
-                                            .process_running_callback()
-                                    });
-                                    Continue(false)
-                                });
-                            }
-                        });
-                        Status::Running(Rc::new(recv_ch))
+                        let (send_ch, recv_ch) = mpsc::channel::<ActionEvent>();
+                        let handle = self
+                            .ctx
+                            .async_select_streaming(&item, running_callback(send_ch));
+                        Status::Running(Rc::new(recv_ch), handle, false)
                     } else {
                         debug!("Item not selectable with nothing");
                         self.status.clone()
@@ -572,6 +749,102 @@ impl MinionsApp {
         self.update_ui();
     }
 
+    /// Run selected item, or start entering text for it
+    fn select_item(&self, item: &Rc<Item>) -> Status {
+        if self.ctx.selectable(item) {
+            let (send_ch, recv_ch) = mpsc::channel::<ActionEvent>();
+            let handle = self
+                .ctx
+                .async_select_streaming(item, running_callback(send_ch));
+            Status::Running(Rc::new(recv_ch), handle, false)
+        } else if self.ctx.selectable_with_text(item) {
+            Status::Entering {
+                item: item.clone(),
+                suggestions: self
+                    .ctx
+                    .suggest_arg(item)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Rc::new)
+                    .collect(),
+                selected_idx: -1,
+                receiver: None,
+            }
+        } else {
+            debug!("Item not selectable with or without text");
+            self.status.clone()
+        }
+    }
+
+    /// Get currently selected item, in filtering or entering status
+    fn selected_item(&self) -> Option<Rc<Item>> {
+        match self.status {
+            Status::Filtering {
+                selected_idx,
+                ref filtered_items,
+                ..
+            }
+            | Status::Entering {
+                selected_idx,
+                suggestions: ref filtered_items,
+                ..
+            } if selected_idx >= 0 => Some(filtered_items[selected_idx as usize].clone()),
+            _ => None,
+        }
+    }
+
+    fn process_keyevent_alternative(&mut self, idx: usize) {
+        trace!("Processing keyevent Alternative: {}", idx);
+        let item = match self.selected_item().and_then(|x| x.alternative_item(idx)) {
+            Some(item) => Rc::new(item),
+            None => {
+                debug!("No alternative action {} for selected item", idx);
+                return;
+            }
+        };
+        if let Status::Filtering {
+            selected_idx,
+            ref filter_text,
+            ref filtered_items,
+            ..
+        } = self.status
+        {
+            let selected = &filtered_items[selected_idx as usize];
+            if let Err(error) =
+                self.matcher
+                    .record(Some(filter_text), self.ctx.reference.as_deref(), selected)
+            {
+                debug!("Unable to record hit: {}", error);
+            }
+        }
+        self.cancel_running();
+        self.status = self.select_item(&item);
+        self.update_ui();
+    }
+
+    fn process_keyevent_menu(&mut self) {
+        trace!("Processing keyevent Menu");
+        match self.selected_item() {
+            Some(ref item) if !item.alternatives.is_empty() => {
+                self.cancel_running();
+                self.ctx.list_actions(item);
+                self.status = Status::Default;
+                self.update_ui();
+            }
+            _ => debug!("No alternative actions for selected item"),
+        }
+    }
+
+    fn process_keyevent_mark(&mut self) {
+        trace!("Processing keyevent Mark");
+        if let Status::Filtering { .. } = self.status {
+            if let Some(item) = self.selected_item() {
+                self.ctx.toggle_marked(&item);
+                self.process_keyevent_move(1);
+            }
+        }
+    }
+
     fn process_keyevent_copy(&mut self) {
         trace!("Process keyevent copy");
         self.status = match self.status.clone() {
@@ -595,14 +868,115 @@ impl MinionsApp {
             }
             status @ _ => status,
         };
-        gtk::main_quit();
+        self.dismiss();
+    }
+
+    /// Quit, or hide the window if running as daemon
+    fn dismiss(&self) {
+        if self.daemon {
+            debug!("Hide window");
+            self.ui.window.hide();
+        } else {
+            gtk::main_quit();
+        }
+    }
+
+    /// Cancel action running in background, if any
+    fn cancel_running(&self) {
+        match self.status {
+            Status::Running(_, ref handle, _)
+            | Status::Entering {
+                receiver: Some((_, ref handle)),
+                ..
+            } => handle.cancel(),
+            _ => (),
+        }
+    }
+
+    /// Reset to initial state and bring up the window
+    pub fn show(&mut self) {
+        self.cancel_running();
+        self.ctx.reset();
+        self.status = Status::Initial;
+        self.update_ui();
+        self.ui.window.present();
+    }
+
+    /// Rank actions by how often they are selected for the kind of
+    /// quick-sent reference
+    fn rank_for_reference(&mut self) {
+        if let Some(ref reference) = self.ctx.reference {
+            self.ctx.list_items = self
+                .matcher
+                .rank_for_reference(reference, &self.ctx.list_items);
+        }
+    }
+
+    /// Bring up the window in quicksend state,
+    /// send `text` if provided, otherwise send clipboard content
+    pub fn show_quicksend(&mut self, text: Option<String>) {
+        self.show();
+        let res = match text {
+            Some(text) => self.ctx.quicksend(&Item {
+                title: text,
+                ..Item::default()
+            }),
+            None => self.ctx.quicksend_from_clipboard(),
+        };
+        self.status = match res {
+            Ok(_) => {
+                self.rank_for_reference();
+                Status::Default
+            }
+            Err(error) => {
+                warn!("Unable to quicksend: {}", error.display_chain());
+                Status::Error(Rc::new(error))
+            }
+        };
+        self.update_ui();
+    }
+
+    /// Reload config and all action items
+    pub fn reload(&mut self) {
+        info!("Reloading context");
+        let config = Config::new(&self.configpath);
+        // statistics may be changed by `minions stats`
+        let matcher = config
+            .partial(&["core"])
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+            .and_then(|global_config| Matcher::from_config(&global_config));
+        match matcher {
+            Ok(matcher) => self.matcher = matcher,
+            Err(error) => warn!("Unable to reload statistics: {}", error),
+        }
+        self.ctx.reload(&config);
+        self.ctx.reset();
+        self.status = Status::Initial;
+        self.update_ui();
+    }
+
+    fn process_command(&mut self, command: Command) {
+        trace!("Processing command: {:?}", command);
+        match command {
+            Command::Show => self.show(),
+            Command::Quicksend(text) => self.show_quicksend(text),
+            Command::Reload => self.reload(),
+            Command::Quit => gtk::main_quit(),
+        }
     }
 
     fn process_keyevent(&mut self, event: &gdk::EventKey) -> Inhibit {
         let key = event.get_keyval();
         let modi = event.get_state();
         trace!("Key pressed: {:?}/{:?}", key, modi);
-        if key == gdk::keys::constants::Return {
+        if key == gdk::keys::constants::Return && modi.contains(gdk::ModifierType::CONTROL_MASK) {
+            self.process_keyevent_alternative(0);
+            Inhibit(true)
+        } else if key == gdk::keys::constants::Return && modi.contains(gdk::ModifierType::MOD1_MASK)
+        {
+            self.process_keyevent_alternative(1);
+            Inhibit(true)
+        } else if key == gdk::keys::constants::Return {
             self.process_keyevent_enter();
             Inhibit(true)
         } else if key == gdk::keys::constants::space {
@@ -629,6 +1003,16 @@ impl MinionsApp {
         {
             self.process_keyevent_copy();
             Inhibit(true)
+        } else if key == gdk::keys::Key::from_glib('s' as u32)
+            && modi.contains(gdk::ModifierType::CONTROL_MASK)
+        {
+            self.process_keyevent_mark();
+            Inhibit(true)
+        } else if key == gdk::keys::Key::from_glib('o' as u32)
+            && modi.contains(gdk::ModifierType::CONTROL_MASK)
+        {
+            self.process_keyevent_menu();
+            Inhibit(true)
         } else if key == gdk::keys::constants::Down {
             self.process_keyevent_move(1);
             Inhibit(true)
@@ -649,19 +1033,12 @@ impl MinionsApp {
 
     pub fn new(
         configpath: &std::path::Path,
+        daemon: bool,
     ) -> &'static thread::LocalKey<RefCell<Option<MinionsApp>>> {
         let config = Config::new(configpath);
         let global_config = config.partial(&["core"]).unwrap();
 
-        println!(
-            "{:?}",
-            &global_config.get_filename(&["statistic_file"]).unwrap()
-        );
-        let matcher = Matcher::new(
-            &global_config.get_filename(&["statistic_file"]).unwrap(),
-            "",
-        )
-        .unwrap();
+        let matcher = Matcher::from_config(&global_config).unwrap();
 
         let ctx = Context::new(&config);
 
@@ -673,8 +1050,14 @@ impl MinionsApp {
                 global_config.get::<u64>(&["filter_timeout"]).unwrap(),
             ),
             matcher,
+            filtering: None,
+            configpath: configpath.to_path_buf(),
+            daemon,
         };
         app.update_ui();
+        if daemon {
+            app.ui.window.hide();
+        }
 
         app.ui.window.connect_key_press_event(move |_, event| {
             APP.with(|app| {
@@ -698,27 +1081,61 @@ impl MinionsApp {
         });
 
         app.ui.window.connect_focus_out_event(move |_, _| {
-            gtk::main_quit();
+            APP.with(|app| {
+                // may be triggered by dismiss() itself, while app is borrowed
+                if let Ok(app) = app.try_borrow() {
+                    if let Some(ref app) = *app {
+                        app.dismiss();
+                    }
+                }
+            });
             Inhibit(false)
         });
 
         app.ui.window.connect_delete_event(move |_, _| {
-            gtk::main_quit();
-            Inhibit(false)
-        });
-
-        let configpath = configpath.to_path_buf();
-        glib::source::unix_signal_add(1, move || {
             APP.with(|app| {
-                let newconfig = Config::new(&configpath);
-                if let Some(ref mut app) = *app.borrow_mut() {
-                    info!("Received SIGHUP, reloading context");
-                    app.ctx.reload(&newconfig);
+                // may be triggered by dismiss() itself, while app is borrowed
+                if let Ok(app) = app.try_borrow() {
+                    if let Some(ref app) = *app {
+                        app.dismiss();
+                    }
                 }
             });
-            Continue(true)
+            Inhibit(true)
         });
 
+        control::set_local_handler(dispatch_command);
+        if daemon {
+            // only the daemon owns the control socket, a foreground instance
+            // must not take over `ctl` and `--send` of a running daemon
+            let control_socket = global_config.get_filename(&["control_socket"]).unwrap();
+            if let Err(error) = control::listen(&control_socket, dispatch_command) {
+                warn!("Control socket unavailable: {}", error.display_chain());
+            }
+
+            let mut bindings = Vec::new();
+            for (name, command) in [
+                ("shortcut_show", Command::Show),
+                ("shortcut_show_quicksend", Command::Quicksend(None)),
+            ]
+            .iter()
+            {
+                let accel = global_config.get::<String>(&[name]).unwrap();
+                if accel.is_empty() {
+                    continue;
+                }
+                match Hotkey::parse(&accel) {
+                    Ok(hotkey) => bindings.push((hotkey, command.clone())),
+                    Err(error) => warn!("Invalid {}: {}", name, error.display_chain()),
+                }
+            }
+            if !bindings.is_empty() {
+                if let Err(error) = hotkey::listen(bindings, dispatch_command) {
+                    warn!("Unable to register shortcuts: {}", error.display_chain());
+                }
+            }
+        }
+
         APP.with(|g_app| *g_app.borrow_mut() = Some(app));
         &APP
     }
@@ -0,0 +1,182 @@
+// Control socket for a running Minions instance
+//
+// Protocol: each connection carries exactly one command. The client writes
+// the command name, optionally followed by a single space and an argument
+// (which may span multiple lines), then shuts down its write half. The server
+// replies with a single line, either "ok" or "error: <message>".
+
+use std::{
+    fs,
+    io::{Read, Write},
+    net::Shutdown,
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    sync::Mutex,
+    thread,
+};
+
+use crate::mcore::errors::*;
+
+lazy_static! {
+    /// Handler of commands from within the process, set by the running frontend
+    static ref LOCAL_HANDLER: Mutex<Option<fn(Command)>> = Mutex::new(None);
+}
+
+/// Commands accepted by the control socket
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Bring up the window in initial state
+    Show,
+    /// Bring up the window in quicksend state, with given text or from clipboard
+    Quicksend(Option<String>),
+    /// Reload config and all action items
+    Reload,
+    /// Exit the process
+    Quit,
+}
+
+impl Command {
+    pub fn parse(text: &str) -> Result<Command> {
+        let mut parts = text.splitn(2, ' ');
+        let name = parts.next().unwrap_or("").trim_end_matches('\n');
+        let arg = parts.next().filter(|x| !x.is_empty());
+        match (name, arg) {
+            ("show", None) => Ok(Command::Show),
+            ("quicksend", arg) => Ok(Command::Quicksend(arg.map(|x| x.into()))),
+            ("reload", None) => Ok(Command::Reload),
+            ("quit", None) => Ok(Command::Quit),
+            _ => bail!("Invalid command: {:?}", text),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        match self {
+            Command::Show => "show".into(),
+            Command::Quicksend(None) => "quicksend".into(),
+            Command::Quicksend(Some(text)) => format!("quicksend {}", text),
+            Command::Reload => "reload".into(),
+            Command::Quit => "quit".into(),
+        }
+    }
+}
+
+/// Send command to the instance listening on `path`
+pub fn send(path: &Path, command: &Command) -> Result<()> {
+    let mut stream = UnixStream::connect(path)
+        .chain_err(|| format!("Unable to connect to control socket {:?}", path))?;
+    stream.write_all(command.to_text().as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    let reply = reply.trim_end();
+    if reply == "ok" {
+        Ok(())
+    } else {
+        bail!("{}", reply.trim_start_matches("error: "))
+    }
+}
+
+/// Set handler of commands from `dispatch`, called from any thread
+pub fn set_local_handler(handler: fn(Command)) {
+    *LOCAL_HANDLER.lock().unwrap() = Some(handler);
+}
+
+/// Send command to the frontend of this process (e.g. from actions), which
+/// may not be listening on the control socket
+pub fn dispatch(command: Command) -> Result<()> {
+    match *LOCAL_HANDLER.lock().unwrap() {
+        Some(handler) => {
+            handler(command);
+            Ok(())
+        }
+        None => bail!("No frontend to handle {:?}", command),
+    }
+}
+
+/// Whether some instance is listening on `path`
+pub fn is_alive(path: &Path) -> bool {
+    UnixStream::connect(path).is_ok()
+}
+
+fn handle_client<F>(mut stream: UnixStream, handler: &F) -> Result<()>
+where
+    F: Fn(Command) + Send + 'static,
+{
+    let mut text = String::new();
+    stream.read_to_string(&mut text)?;
+    match Command::parse(&text) {
+        Ok(command) => {
+            debug!("Received control command: {:?}", command);
+            handler(command);
+            stream.write_all(b"ok\n")?;
+        }
+        Err(error) => {
+            stream.write_all(format!("error: {}\n", error).as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+/// Listen on `path` in a background thread, calling `handler` for every command
+///
+/// `handler` is called in the listening thread, use `glib::idle_add` to get
+/// back to the main loop.
+pub fn listen<F>(path: &Path, handler: F) -> Result<()>
+where
+    F: Fn(Command) + Send + 'static,
+{
+    if path.exists() {
+        if is_alive(path) {
+            bail!("Another instance is already listening on {:?}", path);
+        }
+        debug!("Removing stale control socket {:?}", path);
+        fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(path)
+        .chain_err(|| format!("Unable to bind control socket {:?}", path))?;
+    info!("Listening on control socket {:?}", path);
+
+    thread::Builder::new()
+        .name("control".into())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(error) = handle_client(stream, &handler) {
+                            warn!("Error handling control client: {}", error);
+                        }
+                    }
+                    Err(error) => warn!("Error accepting control client: {}", error),
+                }
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_parse_test() {
+        assert_eq!(Command::parse("show").unwrap(), Command::Show);
+        assert_eq!(Command::parse("reload\n").unwrap(), Command::Reload);
+        assert_eq!(
+            Command::parse("quicksend").unwrap(),
+            Command::Quicksend(None)
+        );
+        assert_eq!(
+            Command::parse("quicksend hello\nworld").unwrap(),
+            Command::Quicksend(Some("hello\nworld".into()))
+        );
+        assert!(Command::parse("show me").is_err());
+        assert!(Command::parse("").is_err());
+
+        let cmd = Command::Quicksend(Some("a b".into()));
+        assert_eq!(Command::parse(&cmd.to_text()).unwrap(), cmd);
+    }
+}
@@ -0,0 +1,228 @@
+// Global hotkeys via X11 key grabs on the root window
+//
+// Grabs are done using a dedicated X connection, events are read in a
+// background thread.
+
+extern crate x11;
+
+use self::x11::xlib;
+
+use std::{
+    ffi::CString,
+    fmt, ptr,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use crate::{frontend::control::Command, mcore::errors::*};
+
+/// A parsed accelerator, e.g. `<Ctrl><Shift>space`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotkey {
+    /// Key name, as accepted by XStringToKeysym
+    pub key: String,
+    /// X modifier mask
+    pub modifiers: u32,
+}
+
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [
+            (xlib::ControlMask, "<Ctrl>"),
+            (xlib::ShiftMask, "<Shift>"),
+            (xlib::Mod1Mask, "<Alt>"),
+            (xlib::Mod3Mask, "<Hyper>"),
+            (xlib::Mod4Mask, "<Super>"),
+            (xlib::Mod5Mask, "<Mod5>"),
+        ];
+        for (mask, name) in names.iter() {
+            if self.modifiers & mask != 0 {
+                write!(f, "{}", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+impl Hotkey {
+    /// Parse accelerator in GTK syntax (see `gtk_accelerator_parse`)
+    pub fn parse(text: &str) -> Result<Hotkey> {
+        let mut modifiers = 0;
+        let mut rest = text.trim();
+        while rest.starts_with('<') {
+            let end = rest
+                .find('>')
+                .ok_or_else(|| Error::from(format!("Unclosed modifier in {:?}", text)))?;
+            modifiers |= match rest[1..end].to_lowercase().as_str() {
+                "ctrl" | "ctl" | "control" | "primary" => xlib::ControlMask,
+                "shift" | "shft" => xlib::ShiftMask,
+                "alt" | "mod1" | "meta" => xlib::Mod1Mask,
+                "mod3" | "hyper" => xlib::Mod3Mask,
+                "mod4" | "super" => xlib::Mod4Mask,
+                "mod5" => xlib::Mod5Mask,
+                modifier => bail!("Unsupported modifier <{}> in {:?}", modifier, text),
+            };
+            rest = &rest[end + 1..];
+        }
+        if rest.is_empty() {
+            bail!("No key in accelerator {:?}", text);
+        }
+        Ok(Hotkey {
+            key: rest.into(),
+            modifiers,
+        })
+    }
+}
+
+/// Set by the X error handler when a grab fails
+static GRAB_FAILED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn on_x_error(_: *mut xlib::Display, event: *mut xlib::XErrorEvent) -> i32 {
+    if (*event).error_code == xlib::BadAccess {
+        GRAB_FAILED.store(true, Ordering::SeqCst);
+    } else {
+        warn!("X error: {}", (*event).error_code);
+    }
+    0
+}
+
+struct Display(*mut xlib::Display);
+
+// the display is only used by the listening thread after grabbing
+unsafe impl Send for Display {}
+
+/// Modifiers which should not affect matching (CapsLock, NumLock)
+const IGNORED_MODIFIERS: [u32; 4] = [
+    0,
+    xlib::LockMask,
+    xlib::Mod2Mask,
+    xlib::LockMask | xlib::Mod2Mask,
+];
+
+unsafe fn grab_one(display: *mut xlib::Display, root: xlib::Window, hotkey: &Hotkey) -> Result<u8> {
+    let key = CString::new(hotkey.key.as_str()).chain_err(|| "Invalid key name")?;
+    let keysym = xlib::XStringToKeysym(key.as_ptr());
+    if keysym == 0 {
+        bail!("Unknown key {:?} in shortcut {}", hotkey.key, hotkey);
+    }
+    let keycode = xlib::XKeysymToKeycode(display, keysym);
+    if keycode == 0 {
+        bail!("No keycode for key {:?} in shortcut {}", hotkey.key, hotkey);
+    }
+
+    GRAB_FAILED.store(false, Ordering::SeqCst);
+    // the error handler is process-wide (shared with GDK), so it's only
+    // installed while grabbing, and the previous one is restored afterwards
+    let previous_handler = xlib::XSetErrorHandler(Some(on_x_error));
+    for ignored in IGNORED_MODIFIERS.iter() {
+        xlib::XGrabKey(
+            display,
+            i32::from(keycode),
+            hotkey.modifiers | ignored,
+            root,
+            xlib::True,
+            xlib::GrabModeAsync,
+            xlib::GrabModeAsync,
+        );
+    }
+    xlib::XSync(display, xlib::False);
+    xlib::XSetErrorHandler(previous_handler);
+    if GRAB_FAILED.load(Ordering::SeqCst) {
+        for ignored in IGNORED_MODIFIERS.iter() {
+            xlib::XUngrabKey(
+                display,
+                i32::from(keycode),
+                hotkey.modifiers | ignored,
+                root,
+            );
+        }
+        bail!(
+            "Shortcut {} is already grabbed by another application",
+            hotkey
+        );
+    }
+    Ok(keycode)
+}
+
+/// Grab all hotkeys, call `handler` with the bound command when pressed
+///
+/// Returns error only if no hotkey can be grabbed at all, failing ones are
+/// reported in log. `handler` is called in the listening thread.
+pub fn listen<F>(bindings: Vec<(Hotkey, Command)>, handler: F) -> Result<()>
+where
+    F: Fn(Command) + Send + 'static,
+{
+    for (i, (hotkey, _)) in bindings.iter().enumerate() {
+        if let Some((other, _)) = bindings[..i].iter().find(|(other, _)| other == hotkey) {
+            bail!("Shortcut {} is bound more than once ({})", hotkey, other);
+        }
+    }
+
+    let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
+    if display.is_null() {
+        bail!("Unable to open X display");
+    }
+
+    let mut grabbed: Vec<(u8, u32, Command)> = Vec::new();
+    unsafe {
+        let root = xlib::XDefaultRootWindow(display);
+        for (hotkey, command) in bindings.into_iter() {
+            match grab_one(display, root, &hotkey) {
+                Ok(keycode) => {
+                    info!("Grabbed shortcut {} for {:?}", hotkey, command);
+                    grabbed.push((keycode, hotkey.modifiers, command));
+                }
+                Err(error) => warn!("Unable to register shortcut: {}", error),
+            }
+        }
+    }
+    if grabbed.is_empty() {
+        unsafe { xlib::XCloseDisplay(display) };
+        bail!("No shortcut registered");
+    }
+
+    let display = Display(display);
+    thread::Builder::new()
+        .name("hotkey".into())
+        .spawn(move || {
+            let display = display;
+            let mut event = xlib::XEvent { pad: [0; 24] };
+            loop {
+                unsafe { xlib::XNextEvent(display.0, &mut event) };
+                if event.get_type() != xlib::KeyPress {
+                    continue;
+                }
+                let key_event = xlib::XKeyEvent::from(event);
+                let modifiers = key_event.state & !(xlib::LockMask | xlib::Mod2Mask);
+                if let Some((_, _, command)) = grabbed.iter().find(|(keycode, mods, _)| {
+                    u32::from(*keycode) == key_event.keycode && *mods == modifiers
+                }) {
+                    debug!("Shortcut pressed: {:?}", command);
+                    handler(command.clone());
+                }
+            }
+        })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkey_parse_test() {
+        let hotkey = Hotkey::parse("<Ctrl><Shift>space").unwrap();
+        assert_eq!(hotkey.key, "space");
+        assert_eq!(hotkey.modifiers, xlib::ControlMask | xlib::ShiftMask);
+        assert_eq!(hotkey.to_string(), "<Ctrl><Shift>space");
+
+        assert_eq!(
+            Hotkey::parse("<control>space").unwrap(),
+            Hotkey::parse("<Primary>space").unwrap()
+        );
+        assert_eq!(Hotkey::parse("F12").unwrap().modifiers, 0);
+        assert!(Hotkey::parse("<Ctrl>").is_err());
+        assert!(Hotkey::parse("<Ctrl space").is_err());
+        assert!(Hotkey::parse("<Release>space").is_err());
+    }
+}
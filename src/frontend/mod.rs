@@ -7,4 +7,7 @@ extern crate gdk;
 extern crate gtk;
 
 pub mod app;
+pub mod control;
+pub mod hotkey;
+pub mod tui;
 pub mod ui;
@@ -0,0 +1,877 @@
+// Terminal frontend, usable without display (e.g. over SSH)
+//
+// Reads keys from and draws to /dev/tty, so that stdin and stdout stay
+// available for dmenu mode (candidates from stdin, selection to stdout).
+
+extern crate libc;
+extern crate nix;
+
+use self::nix::{
+    poll::{poll, PollFd, PollFlags},
+    sys::termios,
+};
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+};
+
+use crate::{
+    frontend::control::{self, Command},
+    mcore::{
+        action::{ActionEvent, ActionResult},
+        cancel::CancelHandle,
+        config::Config,
+        context::Context,
+        errors::*,
+        item::Item,
+        matcher::Matcher,
+    },
+};
+
+use error_chain::ChainedError;
+
+/// Set by "Reload All Actions" (from the action thread), handled in the main loop
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+fn dispatch_command(command: Command) {
+    match command {
+        Command::Reload => RELOAD_REQUESTED.store(true, Ordering::SeqCst),
+        command => debug!("Ignoring command in terminal: {:?}", command),
+    }
+}
+
+/// Raw mode terminal on /dev/tty, restored on drop
+struct Terminal {
+    tty: File,
+    orig_termios: termios::Termios,
+}
+
+impl Terminal {
+    fn new() -> Result<Terminal> {
+        let tty = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .chain_err(|| "Unable to open /dev/tty")?;
+        let orig_termios = termios::tcgetattr(tty.as_raw_fd())
+            .map_err(|e| Error::with_chain(e, "Unable to get terminal attributes"))?;
+        let mut raw = orig_termios.clone();
+        termios::cfmakeraw(&mut raw);
+        termios::tcsetattr(tty.as_raw_fd(), termios::SetArg::TCSANOW, &raw)
+            .map_err(|e| Error::with_chain(e, "Unable to set terminal to raw mode"))?;
+
+        let mut term = Terminal { tty, orig_termios };
+        // alternate screen
+        term.write("\x1b[?1049h");
+        Ok(term)
+    }
+
+    fn size(&self) -> (usize, usize) {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        let ret = unsafe { libc::ioctl(self.tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) };
+        if ret < 0 || size.ws_col == 0 || size.ws_row == 0 {
+            (80, 24)
+        } else {
+            (size.ws_col as usize, size.ws_row as usize)
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        if let Err(error) = self.tty.write_all(text.as_bytes()) {
+            warn!("Unable to write to terminal: {}", error);
+        }
+    }
+
+    /// Wait up to `timeout` milliseconds for input
+    fn read_keys(&mut self, timeout: i32) -> Vec<Key> {
+        let mut fds = [PollFd::new(self.tty.as_raw_fd(), PollFlags::POLLIN)];
+        match poll(&mut fds, timeout) {
+            Ok(n) if n > 0 => {
+                let mut buf = [0u8; 256];
+                match self.tty.read(&mut buf) {
+                    Ok(n) => parse_keys(&buf[..n]),
+                    Err(error) => {
+                        warn!("Unable to read from terminal: {}", error);
+                        Vec::new()
+                    }
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        self.write("\x1b[?1049l\x1b[?25h");
+        let _ = termios::tcsetattr(
+            self.tty.as_raw_fd(),
+            termios::SetArg::TCSANOW,
+            &self.orig_termios,
+        );
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    /// Char (or control char, e.g. '\r' for Enter) pressed with Alt
+    Alt(char),
+}
+
+fn parse_keys(buf: &[u8]) -> Vec<Key> {
+    let mut ret = Vec::new();
+    let text = String::from_utf8_lossy(buf);
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        ret.push(match ch {
+            '\r' => Key::Enter,
+            '\t' => Key::Tab,
+            '\x7f' | '\x08' => Key::Backspace,
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    // CSI: parameter and intermediate bytes, then the final byte
+                    let last = chars.by_ref().find(|c| ('\x40'..='\x7e').contains(c));
+                    match last {
+                        Some('A') => Key::Up,
+                        Some('B') => Key::Down,
+                        _ => continue,
+                    }
+                }
+                Some('O') => match chars.next() {
+                    Some('A') => Key::Up,
+                    Some('B') => Key::Down,
+                    _ => continue,
+                },
+                Some(c) => Key::Alt(c),
+                None => Key::Escape,
+            },
+            '\x01'..='\x1a' => Key::Ctrl((ch as u8 - 1 + b'a') as char),
+            ch if ch.is_control() => continue,
+            ch => Key::Char(ch),
+        });
+    }
+    ret
+}
+
+enum Status {
+    Filtering {
+        filter_text: String,
+        filtered_items: Vec<Rc<Item>>,
+        selected_idx: usize,
+    },
+    Entering {
+        item: Rc<Item>,
+        text: String,
+        suggestions: Vec<Rc<Item>>,
+        selected_idx: Option<usize>,
+        receiver: Option<(mpsc::Receiver<ActionResult>, CancelHandle)>,
+        /// text of the running realtime query
+        receiver_text: String,
+    },
+    Running(mpsc::Receiver<ActionResult>, CancelHandle),
+    Error(Error),
+}
+
+pub struct TuiApp {
+    term: Terminal,
+    ctx: Context,
+    matcher: Matcher,
+    status: Status,
+    /// Config file to reload from, None in dmenu mode
+    configpath: Option<PathBuf>,
+
+    /// dmenu mode: print selected title to stdout instead of running actions
+    dmenu: bool,
+    /// Exit code if set
+    exit_code: Option<i32>,
+    /// Text to print to stdout after exit
+    output: Option<String>,
+    /// Whether screen should be redrawn, and the terminal size last drawn with
+    dirty: bool,
+    size: (usize, usize),
+}
+
+/// Cut `text` to first line with at most `width` chars
+fn truncate(text: &str, width: usize) -> String {
+    text.lines()
+        .next()
+        .unwrap_or("")
+        .chars()
+        .take(width)
+        .collect()
+}
+
+impl TuiApp {
+    fn new(config: &Config, ctx: Context, dmenu: bool) -> Result<TuiApp> {
+        let global_config = config.partial(&["core"]).unwrap();
+        let matcher =
+            Matcher::from_config(&global_config).chain_err(|| "Unable to load statistics")?;
+
+        Ok(TuiApp {
+            term: Terminal::new()?,
+            status: Status::Filtering {
+                filter_text: String::new(),
+                filtered_items: ctx.list_items.clone(),
+                selected_idx: 0,
+            },
+            ctx,
+            matcher,
+            configpath: None,
+            dmenu,
+            exit_code: None,
+            output: None,
+            dirty: true,
+            size: (0, 0),
+        })
+    }
+
+    /// Run with all actions from config at `configpath`, return exit code
+    pub fn run_actions(configpath: &Path) -> Result<i32> {
+        let config = Config::new(configpath);
+        let ctx = Context::new(&config);
+        let mut app = TuiApp::new(&config, ctx, false)?;
+        app.configpath = Some(configpath.to_path_buf());
+        control::set_local_handler(dispatch_command);
+        app.run()
+    }
+
+    /// Run as dmenu: choose one from `lines`, print to stdout, return exit code
+    pub fn run_dmenu(config: &Config, lines: Vec<String>) -> Result<i32> {
+        let items = lines
+            .into_iter()
+            .filter(|x| !x.is_empty())
+            .map(|x| Item {
+                title: x,
+                ..Item::default()
+            })
+            .collect();
+        let ctx = Context::with_items(config, items);
+        let mut app = TuiApp::new(config, ctx, true)?;
+        let code = app.run()?;
+        let output = app.output.take();
+        // restore terminal before printing
+        drop(app);
+        if let Some(output) = output {
+            println!("{}", output);
+        }
+        Ok(code)
+    }
+
+    fn run(&mut self) -> Result<i32> {
+        loop {
+            if self.dirty || self.size != self.term.size() {
+                self.draw();
+                self.dirty = false;
+            }
+            for key in self.term.read_keys(50) {
+                self.process_key(key);
+                self.dirty = true;
+            }
+            self.process_receivers();
+            if RELOAD_REQUESTED.swap(false, Ordering::SeqCst) {
+                // stay open, although the reload action finishes with nothing
+                self.reload();
+                self.exit_code = None;
+                self.dirty = true;
+            }
+            if let Some(code) = self.exit_code {
+                return Ok(code);
+            }
+        }
+    }
+
+    /// Reload config and all action items
+    fn reload(&mut self) {
+        let configpath = match self.configpath {
+            Some(ref configpath) => configpath.clone(),
+            None => return,
+        };
+        info!("Reloading context");
+        let config = Config::new(&configpath);
+        // statistics may be changed by `minions stats`
+        match config.partial(&["core"]) {
+            Ok(global_config) => match Matcher::from_config(&global_config) {
+                Ok(matcher) => self.matcher = matcher,
+                Err(error) => warn!("Unable to reload statistics: {}", error),
+            },
+            Err(error) => warn!("Unable to reload statistics: {}", error),
+        }
+        self.ctx.reload(&config);
+        self.ctx.reset();
+        self.reset_filter();
+    }
+
+    fn reset_filter(&mut self) {
+        self.cancel_running();
+        self.status = Status::Filtering {
+            filter_text: String::new(),
+            filtered_items: self.ctx.list_items.clone(),
+            selected_idx: 0,
+        };
+    }
+
+    fn set_filter(&mut self, filter_text: String) {
+        let filtered_items = if filter_text.is_empty() {
+            self.ctx.list_items.clone()
+        } else {
+            let task = self
+                .matcher
+                .sort_task(&filter_text, &self.ctx.list_items, self.ctx.list_scorer)
+                .with_reference(self.ctx.reference.as_deref());
+            task.run()
+                .ok()
+                .and_then(|indices| self.matcher.resolve(&task, &indices))
+                .unwrap_or_default()
+        };
+        self.status = Status::Filtering {
+            filter_text,
+            filtered_items,
+            selected_idx: 0,
+        };
+    }
+
+    /// Rank actions by how often they are selected for the kind of
+    /// quick-sent reference
+    fn rank_for_reference(&mut self) {
+        if let Some(ref reference) = self.ctx.reference {
+            self.ctx.list_items = self
+                .matcher
+                .rank_for_reference(reference, &self.ctx.list_items);
+        }
+    }
+
+    fn start_entering(&mut self, item: Rc<Item>) {
+        self.status = Status::Entering {
+            suggestions: self
+                .ctx
+                .suggest_arg(&item)
+                .unwrap_or_default()
+                .into_iter()
+                .map(Rc::new)
+                .collect(),
+            item,
+            text: String::new(),
+            selected_idx: None,
+            receiver: None,
+            receiver_text: String::new(),
+        };
+    }
+
+    fn select(&mut self, item: &Item, text: Option<&str>) {
+        let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
+        let callback = move |res: ActionResult| {
+            if let Err(error) = send_ch.send(res) {
+                debug!("Unable to send to channel: {}", error);
+            }
+        };
+        let handle = if let Some(text) = text {
+            self.ctx.async_select_with_text(item, text, callback)
+        } else {
+            self.ctx.async_select(item, callback)
+        };
+        self.cancel_running();
+        self.status = Status::Running(recv_ch, handle);
+    }
+
+    fn select_marked(&mut self, filter_text: &str) {
+        let items = self.ctx.marked_items.clone();
+        for item in items.iter() {
+            if self.ctx.selectable(item) {
+                self.record(filter_text, item);
+            }
+        }
+        let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
+        let handle = self.ctx.async_select_marked(move |event| {
+            if let ActionEvent::Done(res) = event {
+                if let Err(error) = send_ch.send(res) {
+                    debug!("Unable to send to channel: {}", error);
+                }
+            }
+        });
+        self.cancel_running();
+        self.status = Status::Running(recv_ch, handle);
+    }
+
+    /// Cancel action running in background, if any
+    fn cancel_running(&self) {
+        match self.status {
+            Status::Running(_, ref handle)
+            | Status::Entering {
+                receiver: Some((_, ref handle)),
+                ..
+            } => handle.cancel(),
+            _ => (),
+        }
+    }
+
+    fn process_key(&mut self, key: Key) {
+        trace!("Terminal key: {:?}", key);
+        if key == Key::Ctrl('c') {
+            self.exit_code = Some(1);
+            return;
+        }
+        match self.status {
+            Status::Filtering { .. } => self.process_key_filtering(key),
+            Status::Entering { .. } => self.process_key_entering(key),
+            Status::Running(..) => {
+                if key == Key::Escape {
+                    debug!("Cancel running action");
+                    self.reset_filter();
+                }
+            }
+            Status::Error(_) => {
+                if key == Key::Escape || key == Key::Enter {
+                    self.reset_filter();
+                }
+            }
+        }
+    }
+
+    fn process_key_filtering(&mut self, key: Key) {
+        let (filter_text, filtered_items, selected_idx) = match self.status {
+            Status::Filtering {
+                ref filter_text,
+                ref filtered_items,
+                selected_idx,
+            } => (filter_text.clone(), filtered_items.clone(), selected_idx),
+            _ => return,
+        };
+        let selected = filtered_items.get(selected_idx).cloned();
+
+        match key {
+            Key::Char(' ') if !self.dmenu => {
+                // filter text as trigger keyword takes precedence over the
+                // selected item
+                let item = self.ctx.find_trigger(&filter_text).or(selected);
+                if let Some(item) = item {
+                    if self.ctx.selectable_with_text(&item) {
+                        self.record(&filter_text, &item);
+                        self.start_entering(item);
+                    }
+                }
+            }
+            Key::Char(ch) => {
+                let mut filter_text = filter_text;
+                filter_text.push(ch);
+                self.set_filter(filter_text);
+            }
+            Key::Backspace => {
+                let mut filter_text = filter_text;
+                filter_text.pop();
+                self.set_filter(filter_text);
+            }
+            Key::Up | Key::Ctrl('k') | Key::Ctrl('p') => {
+                self.set_selected_idx(selected_idx.saturating_sub(1));
+            }
+            Key::Down | Key::Ctrl('j') | Key::Ctrl('n') => {
+                let max_idx = filtered_items.len().saturating_sub(1);
+                self.set_selected_idx(std::cmp::min(selected_idx + 1, max_idx));
+            }
+            Key::Ctrl('s') => {
+                if let Some(item) = selected {
+                    self.ctx.toggle_marked(&item);
+                    let max_idx = filtered_items.len().saturating_sub(1);
+                    self.set_selected_idx(std::cmp::min(selected_idx + 1, max_idx));
+                }
+            }
+            Key::Tab
+                if !self.dmenu
+                    && !self.ctx.marked_items.is_empty()
+                    && self.ctx.reference.is_none() =>
+            {
+                match self.ctx.quicksend_marked() {
+                    Ok(_) => {
+                        self.rank_for_reference();
+                        self.reset_filter();
+                    }
+                    Err(error) => self.status = Status::Error(error),
+                }
+            }
+            Key::Tab if !self.dmenu => {
+                if let Some(item) = selected {
+                    if self.ctx.reference.is_none() {
+                        self.record(&filter_text, &item);
+                        match self.ctx.quicksend(&item) {
+                            Ok(_) => {
+                                self.rank_for_reference();
+                                self.reset_filter();
+                            }
+                            Err(error) => self.status = Status::Error(error),
+                        }
+                    }
+                }
+            }
+            Key::Ctrl('o') if !self.dmenu => {
+                if let Some(item) = selected {
+                    if !item.alternatives.is_empty() {
+                        self.record(&filter_text, &item);
+                        self.ctx.list_actions(&item);
+                        self.reset_filter();
+                    }
+                }
+            }
+            Key::Alt('\r') if !self.dmenu => {
+                // run the first alternative action, like Alt+Enter in GUI
+                if let Some((selected, item)) = selected
+                    .and_then(|selected| Some((selected.clone(), selected.alternative_item(0)?)))
+                {
+                    self.record(&filter_text, &selected);
+                    let item = Rc::new(item);
+                    if self.ctx.selectable(&item) {
+                        self.select(&item, None);
+                    } else if self.ctx.selectable_with_text(&item) {
+                        self.start_entering(item);
+                    }
+                }
+            }
+            Key::Enter => {
+                if self.dmenu && !self.ctx.marked_items.is_empty() {
+                    self.output = Some(self.ctx.marked_reference());
+                    self.exit_code = Some(0);
+                } else if self.ctx.selectable_marked() {
+                    self.select_marked(&filter_text);
+                } else if self.dmenu {
+                    let output = match selected {
+                        Some(ref item) => {
+                            self.record(&filter_text, item);
+                            item.title.clone()
+                        }
+                        None => filter_text,
+                    };
+                    self.output = Some(output);
+                    self.exit_code = Some(0);
+                } else if let Some((keyword, item, arg)) = self.ctx.split_trigger(&filter_text) {
+                    // "keyword argument" runs the triggered item with argument
+                    self.record(keyword, &item);
+                    self.select(&item, Some(arg));
+                } else if let Some(item) = selected {
+                    self.record(&filter_text, &item);
+                    if self.ctx.selectable(&item) {
+                        self.select(&item, None);
+                    } else if self.ctx.selectable_with_text(&item) {
+                        self.start_entering(item);
+                    }
+                }
+            }
+            Key::Escape => {
+                if !filter_text.is_empty() {
+                    self.reset_filter();
+                } else if self.ctx.reference.is_some() {
+                    self.ctx.reset();
+                    self.reset_filter();
+                } else {
+                    self.exit_code = Some(1);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn process_key_entering(&mut self, key: Key) {
+        let mut to_select: Option<(Rc<Item>, Option<String>)> = None;
+        if let Status::Entering {
+            ref item,
+            ref mut text,
+            ref suggestions,
+            ref mut selected_idx,
+            ..
+        } = self.status
+        {
+            match key {
+                Key::Char(ch) => {
+                    text.push(ch);
+                    *selected_idx = None;
+                }
+                Key::Backspace => {
+                    text.pop();
+                    *selected_idx = None;
+                }
+                Key::Up | Key::Ctrl('k') | Key::Ctrl('p') => {
+                    *selected_idx = match *selected_idx {
+                        Some(0) | None => None,
+                        Some(idx) => Some(idx - 1),
+                    };
+                }
+                Key::Down | Key::Ctrl('j') | Key::Ctrl('n') => {
+                    if !suggestions.is_empty() {
+                        *selected_idx = match *selected_idx {
+                            None => Some(0),
+                            Some(idx) => Some(std::cmp::min(idx + 1, suggestions.len() - 1)),
+                        };
+                    }
+                }
+                Key::Enter => {
+                    to_select = match *selected_idx {
+                        None => Some((item.clone(), Some(text.clone()))),
+                        Some(idx) => Some((suggestions[idx].clone(), None)),
+                    };
+                }
+                Key::Escape => {
+                    self.reset_filter();
+                    return;
+                }
+                _ => return,
+            }
+        }
+
+        match to_select {
+            Some((item, Some(text))) => self.select(&item, Some(&text)),
+            Some((item, None)) => {
+                if self.ctx.selectable(&item) {
+                    self.select(&item, None);
+                }
+            }
+            None => self.run_realtime(),
+        }
+    }
+
+    /// Start running realtime suggestion for current text, if not running yet
+    fn run_realtime(&mut self) {
+        if let Status::Entering {
+            ref item,
+            ref text,
+            ref mut receiver,
+            ref mut receiver_text,
+            ..
+        } = self.status
+        {
+            if receiver.is_some() || text.is_empty() || !self.ctx.runnable_with_text_realtime(item)
+            {
+                return;
+            }
+            let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
+            let handle =
+                self.ctx
+                    .async_run_with_text_realtime(item, text, move |res: ActionResult| {
+                        if let Err(error) = send_ch.send(res) {
+                            debug!("Unable to send to channel: {}", error);
+                        }
+                    });
+            *receiver = Some((recv_ch, handle));
+            *receiver_text = text.clone();
+        }
+    }
+
+    fn process_receivers(&mut self) {
+        let mut rerun_realtime = false;
+        let mut new_status = None;
+        match self.status {
+            Status::Running(ref receiver, _) => {
+                if let Ok(res) = receiver.try_recv() {
+                    match res {
+                        Ok(items) => {
+                            if items.is_empty() {
+                                debug!("No more listing items!");
+                                self.exit_code = Some(0);
+                            }
+                            self.ctx.async_select_callback(items);
+                            new_status = Some(None);
+                        }
+                        Err(error) => new_status = Some(Some(Status::Error(error))),
+                    }
+                }
+            }
+            Status::Entering {
+                ref text,
+                ref mut suggestions,
+                ref mut selected_idx,
+                ref mut receiver,
+                ref receiver_text,
+                ..
+            } => {
+                let res = receiver.as_ref().and_then(|x| x.0.try_recv().ok());
+                if let Some(res) = res {
+                    match res {
+                        Ok(items) => {
+                            *suggestions = items.into_iter().map(Rc::new).collect();
+                            *selected_idx = None;
+                        }
+                        Err(Error(ErrorKind::Timeout(_), _)) => debug!("Realtime text timed out"),
+                        Err(error) => {
+                            warn!("Error running realtime text: {}", error.display_chain())
+                        }
+                    }
+                    *receiver = None;
+                    rerun_realtime = text != receiver_text;
+                    self.dirty = true;
+                }
+            }
+            _ => (),
+        }
+        if new_status.is_some() {
+            self.dirty = true;
+        }
+        match new_status {
+            Some(Some(status)) => self.status = status,
+            Some(None) => self.reset_filter(),
+            None => (),
+        }
+        if rerun_realtime {
+            self.run_realtime();
+        }
+    }
+
+    fn set_selected_idx(&mut self, idx: usize) {
+        if let Status::Filtering {
+            ref mut selected_idx,
+            ..
+        } = self.status
+        {
+            *selected_idx = idx;
+        }
+    }
+
+    fn record(&mut self, filter_text: &str, item: &Item) {
+        if let Err(error) =
+            self.matcher
+                .record(Some(filter_text), self.ctx.reference.as_deref(), item)
+        {
+            warn!("Unable to record hit: {}", error);
+        }
+    }
+
+    fn draw_item(&self, out: &mut String, item: &Item, selected: bool, width: usize) {
+        let indicator = if self.ctx.is_marked(item) {
+            "*"
+        } else if self.dmenu {
+            " "
+        } else if self.ctx.selectable(item) {
+            ">"
+        } else if self.ctx.selectable_with_text(item) {
+            "A"
+        } else {
+            " "
+        };
+        let mut line = format!("{} {}", indicator, truncate(&item.title, width));
+        if let Some(ref badge) = item.badge {
+            line += &format!(" [{}]", badge);
+        }
+        let mut line = truncate(&line, width);
+        if let Some(ref subtitle) = item.subtitle {
+            let room = width.saturating_sub(line.chars().count() + 3);
+            if room > 0 && !subtitle.is_empty() {
+                line += &format!("\x1b[2m - {}\x1b[22m", truncate(subtitle, room));
+            }
+        }
+        if selected {
+            out.push_str(&format!("\x1b[7m{}\x1b[27m\r\n", line));
+        } else {
+            out.push_str(&format!("{}\r\n", line));
+        }
+    }
+
+    fn draw(&mut self) {
+        self.size = self.term.size();
+        let (width, height) = self.size;
+        let mut out = String::from("\x1b[H\x1b[2J");
+
+        if let Some(ref reference) = self.ctx.reference {
+            out.push_str(&format!(
+                "\x1b[1mQuicksend:\x1b[22m {}\r\n",
+                truncate(reference, width.saturating_sub(11))
+            ));
+        }
+
+        let (prompt, items, selected_idx) = match self.status {
+            Status::Filtering {
+                ref filter_text,
+                ref filtered_items,
+                selected_idx,
+            } => (
+                format!("> {}", filter_text),
+                filtered_items.clone(),
+                Some(selected_idx),
+            ),
+            Status::Entering {
+                ref item,
+                ref text,
+                ref suggestions,
+                selected_idx,
+                ref receiver,
+                ..
+            } => (
+                format!(
+                    "[{}]{} > {}",
+                    item.title,
+                    if receiver.is_some() { "*" } else { "" },
+                    text
+                ),
+                suggestions.clone(),
+                selected_idx,
+            ),
+            Status::Running(..) => ("Running... (Esc to drop)".into(), Vec::new(), None),
+            Status::Error(ref error) => {
+                out.push_str("\x1b[31m");
+                for line in error.display_chain().to_string().lines() {
+                    out.push_str(&format!("{}\r\n", truncate(line, width)));
+                }
+                out.push_str("\x1b[39m");
+                ("(Esc to go back)".into(), Vec::new(), None)
+            }
+        };
+        out.push_str(&format!("\x1b[1m{}\x1b[22m\r\n", truncate(&prompt, width)));
+
+        let rows = height.saturating_sub(out.matches("\r\n").count() + 1);
+        let start = match selected_idx {
+            Some(idx) if idx >= rows => idx + 1 - rows,
+            _ => 0,
+        };
+        for (idx, item) in items.iter().enumerate().skip(start).take(rows) {
+            self.draw_item(&mut out, item, Some(idx) == selected_idx, width);
+        }
+        self.term.write(&out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_keys_test() {
+        assert_eq!(
+            parse_keys(b"a\x1b[A\x1b[B\r\x7f\x0a"),
+            vec![
+                Key::Char('a'),
+                Key::Up,
+                Key::Down,
+                Key::Enter,
+                Key::Backspace,
+                Key::Ctrl('j')
+            ]
+        );
+        assert_eq!(parse_keys(b"\x1b"), vec![Key::Escape]);
+        assert_eq!(
+            parse_keys(b"\x1b[3~\x1b[1;5Ab\x1bOB"),
+            vec![Key::Up, Key::Char('b'), Key::Down]
+        );
+        assert_eq!(
+            parse_keys(b"\x1b\r\x1bx"),
+            vec![Key::Alt('\r'), Key::Alt('x')]
+        );
+        assert_eq!(
+            parse_keys(b"\x0b\x03"),
+            vec![Key::Ctrl('k'), Key::Ctrl('c')]
+        );
+        assert_eq!(
+            parse_keys("你 ".as_bytes()),
+            vec![Key::Char('你'), Key::Char(' ')]
+        );
+    }
+}
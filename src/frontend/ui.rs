@@ -13,6 +13,7 @@ use crate::mcore::{
     context::Context,
     errors::Error,
     item::{Icon, Item},
+    scorer::ScorerKind,
 };
 
 use self::{gdk_pixbuf::prelude::*, lru_cache::LruCache};
@@ -44,6 +45,8 @@ pub struct MinionsUI {
 
     gtkbuf_cache: RefCell<LruCache<PathBuf, Option<gdk_pixbuf::Pixbuf>>>,
     items: Vec<ItemUI>,
+    /// Current filter text, to highlight matches in items
+    filter_text: RefCell<String>,
 }
 
 lazy_static! {
@@ -56,6 +59,33 @@ const ICON_SIZE: i32 = 45;
 const ICON_FONT_SIZE: i32 = 28;
 const GTKBUF_CACHE_SIZE: usize = 128;
 
+/// Escaped pango markup of `text`, with chars at `positions` emphasized
+fn highlight_markup(text: &str, positions: &[usize]) -> String {
+    let mut ret = String::new();
+    let mut emphasized = false;
+    let mut buf = [0; 4];
+    for (idx, ch) in text.chars().enumerate() {
+        let matched = positions.binary_search(&idx).is_ok();
+        if matched != emphasized {
+            ret += if matched { "<b><u>" } else { "</u></b>" };
+            emphasized = matched;
+        }
+        ret += &glib::markup_escape_text(ch.encode_utf8(&mut buf));
+    }
+    if emphasized {
+        ret += "</u></b>";
+    }
+    ret
+}
+
+/// Escaped pango markup of `text`, with chars matching `pattern` emphasized
+fn match_markup(text: &str, pattern: &str, scorer: ScorerKind) -> String {
+    highlight_markup(
+        text,
+        &scorer.scorer().score_positions(text, pattern, false).1,
+    )
+}
+
 impl MinionsUI {
     pub fn new() -> MinionsUI {
         let builder = gtk::Builder::from_string(include_str!("resource/minions.glade"));
@@ -106,6 +136,7 @@ impl MinionsUI {
             action_label: builder.get_object::<gtk::Label>("action_name").unwrap(),
             gtkbuf_cache: RefCell::new(LruCache::new(GTKBUF_CACHE_SIZE)),
             items,
+            filter_text: RefCell::new(String::new()),
         }
     }
 
@@ -245,6 +276,7 @@ impl MinionsUI {
 
     pub fn set_filter_text(&self, text: &str) {
         self.filter_label.set_text(text);
+        *self.filter_text.borrow_mut() = text.into();
     }
 
     pub fn set_error(&self, error: &Error) {
@@ -290,8 +322,11 @@ impl MinionsUI {
 
     fn update_item(&self, idx: usize, item: &Item, ctx: &Context) {
         let item_ui = &self.items[idx];
+        let filter_text = self.filter_text.borrow();
 
-        item_ui.title.set_text(&item.title);
+        item_ui
+            .title
+            .set_markup(&match_markup(&item.title, &filter_text, ctx.list_scorer));
         if let Some(ref ico) = item.icon {
             self.set_image_icon(&item_ui.icon, &item_ui.icon_text, ico);
         } else {
@@ -306,7 +341,11 @@ impl MinionsUI {
             Some(ref text) => {
                 if text.len() > 0 {
                     item_ui.subtitle.show();
-                    item_ui.subtitle.set_text(&text);
+                    item_ui.subtitle.set_markup(&match_markup(
+                        &text,
+                        &filter_text,
+                        ctx.list_scorer,
+                    ));
                 } else {
                     item_ui.subtitle.hide();
                 }
@@ -321,7 +360,17 @@ impl MinionsUI {
             None => item_ui.badge.hide(),
         }
 
-        if ctx.selectable(&item) {
+        let style = item_ui.title.get_style_context();
+        if ctx.is_marked(item) {
+            style.add_class("marked");
+        } else {
+            style.remove_class("marked");
+        }
+
+        if ctx.is_marked(item) {
+            // fontawesome "check"
+            item_ui.selectable.set_text("\u{f00c}");
+        } else if ctx.selectable(&item) {
             item_ui.selectable.set_text(">");
         } else if ctx.selectable_with_text(&item) {
             item_ui.selectable.set_text("A");
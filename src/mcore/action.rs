@@ -3,11 +3,19 @@
 // @Last Modified by:   BlahGeek
 // @Last Modified time: 2018-04-08
 
-use crate::mcore::{errors::Result, item::Item};
-use std::sync::Arc;
+use crate::mcore::{errors::Result, item::Item, scorer::ScorerKind};
+use std::{sync::Arc, time::Duration};
 
 pub type ActionResult = Result<Vec<Item>>;
 
+/// Event from an action running in background
+pub enum ActionEvent {
+    /// A batch of new items, emitted while the action is still running
+    Batch(Vec<Item>),
+    /// The action is done, with all items (including emitted ones)
+    Done(ActionResult),
+}
+
 /// The general action type
 pub trait Action {
     /// Whether this action runs without input
@@ -38,6 +46,16 @@ pub trait Action {
         None
     }
 
+    /// Max time allowed for a single run, None to use the global default
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Scorer to filter the results, None to use the global default
+    fn scorer(&self) -> Option<ScorerKind> {
+        None
+    }
+
     /// Run realtime (auto-complete)
     fn run_arg_realtime(&self, _: &str) -> ActionResult {
         unimplemented!()
@@ -52,6 +70,20 @@ pub trait Action {
     fn run_arg(&self, _: &str) -> ActionResult {
         unimplemented!()
     }
+
+    /// Run the action without input, calling `emit` with items as soon as
+    /// they are available. Default to emit all items from `run_bare` at once
+    fn run_bare_streaming(&self, emit: &dyn Fn(Vec<Item>)) -> Result<()> {
+        emit(self.run_bare()?);
+        Ok(())
+    }
+
+    /// Run the action with text input, calling `emit` with items as soon as
+    /// they are available. Default to emit all items from `run_arg` at once
+    fn run_arg_streaming(&self, text: &str, emit: &dyn Fn(Vec<Item>)) -> Result<()> {
+        emit(self.run_arg(text)?);
+        Ok(())
+    }
 }
 
 /// An actiton with arg
@@ -81,10 +113,25 @@ impl Action for PartialAction {
         true
     }
 
+    fn timeout(&self) -> Option<Duration> {
+        self.action.timeout()
+    }
+
+    fn scorer(&self) -> Option<ScorerKind> {
+        self.action.scorer()
+    }
+
     fn run_bare(&self) -> ActionResult {
         if let Some(ref f) = self.run_callback {
             f();
         }
         self.action.run_arg(&self.arg)
     }
+
+    fn run_bare_streaming(&self, emit: &dyn Fn(Vec<Item>)) -> Result<()> {
+        if let Some(ref f) = self.run_callback {
+            f();
+        }
+        self.action.run_arg_streaming(&self.arg, emit)
+    }
 }
@@ -0,0 +1,130 @@
+// Cooperative cancellation of running actions
+//
+// Context creates a handle for each action run and makes it available to the
+// action (via `current()`) in the thread running it. Long-running actions
+// should either poll `is_cancelled()`/`check()`, or register a hook via
+// `on_cancel()` to abort blocking work (e.g. killing a child process).
+
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use crate::mcore::errors::*;
+
+type Hook = Box<dyn FnOnce() + Send + 'static>;
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    hooks: Mutex<Vec<Hook>>,
+}
+
+/// Handle to cancel a running action, cheap to clone
+#[derive(Clone, Default)]
+pub struct CancelHandle {
+    inner: Arc<Inner>,
+}
+
+impl CancelHandle {
+    pub fn new() -> CancelHandle {
+        CancelHandle::default()
+    }
+
+    /// Request cancellation, run all registered hooks
+    pub fn cancel(&self) {
+        if self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        debug!("Cancelling action");
+        let hooks: Vec<Hook> = self.inner.hooks.lock().unwrap().drain(..).collect();
+        for hook in hooks {
+            hook();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Return `ErrorKind::Cancelled` if cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(ErrorKind::Cancelled.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Register hook to run on cancellation, run it now if already cancelled
+    pub fn on_cancel<F>(&self, hook: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let mut hooks = self.inner.hooks.lock().unwrap();
+        if self.is_cancelled() {
+            drop(hooks);
+            hook();
+        } else {
+            hooks.push(Box::new(hook));
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancelHandle>> = const { RefCell::new(None) };
+}
+
+/// Handle of the action running in current thread
+/// (a handle which is never cancelled if there's none)
+pub fn current() -> CancelHandle {
+    CURRENT.with(|current| current.borrow().clone().unwrap_or_default())
+}
+
+/// Set handle for actions running in current thread
+pub fn set_current(handle: Option<CancelHandle>) {
+    CURRENT.with(|current| *current.borrow_mut() = handle);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn cancel_test() {
+        let handle = CancelHandle::new();
+        let count = Arc::new(AtomicUsize::new(0));
+        let count_ = count.clone();
+        handle.on_cancel(move || {
+            count_.fetch_add(1, Ordering::SeqCst);
+        });
+        assert!(handle.check().is_ok());
+
+        handle.clone().cancel();
+        handle.cancel();
+        assert!(handle.is_cancelled());
+        assert!(handle.check().is_err());
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+
+        let count_ = count.clone();
+        handle.on_cancel(move || {
+            count_.fetch_add(1, Ordering::SeqCst);
+        });
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn current_test() {
+        assert!(!current().is_cancelled());
+        let handle = CancelHandle::new();
+        set_current(Some(handle.clone()));
+        handle.cancel();
+        assert!(current().is_cancelled());
+        set_current(None);
+        assert!(!current().is_cancelled());
+    }
+}
@@ -67,6 +67,8 @@ impl<'a> ConfigValue<'a> {
 pub struct Config {
     default: toml::Value,
     user: Option<toml::Value>,
+    /// Directory of the config file, to resolve relative paths in it
+    dir: std::path::PathBuf,
 }
 
 impl Config {
@@ -84,6 +86,7 @@ impl Config {
         Config {
             default: defaultconfig,
             user: userconfig,
+            dir: p.parent().map(|x| x.to_path_buf()).unwrap_or_default(),
         }
     }
 
@@ -113,6 +116,11 @@ impl Config {
         Ok(p)
     }
 
+    /// Directory of the config file
+    pub fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+
     pub fn partial(&self, path: &[&str]) -> Result<Self, ConfigGetError> {
         let mut userval = ConfigValue::new(self.user.as_ref());
         let mut defaultval = ConfigValue::new(Some(&self.default));
@@ -123,6 +131,7 @@ impl Config {
         Ok(Config {
             default: defaultval.into_result::<toml::Value>()?,
             user: userval.into_result::<toml::Value>().ok(),
+            dir: self.dir.clone(),
         })
     }
 }
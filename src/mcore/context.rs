@@ -12,42 +12,89 @@ use self::uuid::Uuid;
 use crate::{
     actions,
     mcore::{
-        action::{ActionResult, PartialAction},
+        action::{Action, ActionEvent, ActionResult, PartialAction},
+        cancel::{self, CancelHandle},
         config::Config,
         errors::*,
         item::Item,
         lrudb::LruDB,
+        scorer::ScorerKind,
     },
 };
-use std::{rc::Rc, sync::Arc, thread};
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use error_chain::ChainedError;
 
 pub struct Context {
     /// Reference data for quick-send
     pub reference: Option<String>,
     /// Candidates items list
     pub list_items: Vec<Rc<Item>>,
+    /// Marked items in list, for batch running or quick-send
+    pub marked_items: Vec<Rc<Item>>,
+    /// Scorer to filter candidates items
+    pub list_scorer: ScorerKind,
 
     /// Cached all actions
     action_items: Vec<Rc<Item>>,
 
     lrudb: Arc<LruDB>,
     history_max_n: i32,
+    /// Default timeout for running actions
+    action_timeout: Option<Duration>,
+    /// Default scorer
+    scorer: ScorerKind,
+    /// Scorer requested by the running action, for its results
+    running_scorer: Cell<Option<ScorerKind>>,
 }
 
 impl Context {
     /// Create context with initial items
     pub fn new(config: &Config) -> Context {
-        let db_file = config.get_filename(&["core", "db_file"]).unwrap();
+        let mut ctx = Context::with_items(config, Vec::new());
+        ctx.reload(config);
+        ctx.reset();
+        ctx
+    }
+
+    /// Create context with given items instead of all actions
+    pub fn with_items(config: &Config, items: Vec<Item>) -> Context {
         let history_max_n = config.get::<i32>(&["core", "history"]).unwrap();
+        let action_timeout = match config.get::<u64>(&["core", "action_timeout"]).unwrap() {
+            0 => None,
+            ms => Some(Duration::from_millis(ms)),
+        };
+        let scorer = config
+            .get::<ScorerKind>(&["core", "scorer"])
+            .unwrap_or_else(|error| {
+                warn!("Invalid scorer, using default: {}", error);
+                ScorerKind::default()
+            });
 
         let mut ctx = Context {
             reference: None,
             list_items: Vec::new(),
-            action_items: Vec::new(),
-            lrudb: Arc::new(LruDB::new(Some(&db_file)).unwrap()),
+            marked_items: Vec::new(),
+            list_scorer: scorer,
+            action_items: items.into_iter().map(Rc::new).collect(),
+            lrudb: Arc::new(LruDB::from_config(config).unwrap_or_else(|error| {
+                warn!(
+                    "Unable to open database, history is not saved: {}",
+                    error.display_chain()
+                );
+                LruDB::new(None).expect("Unable to open in-memory database")
+            })),
             history_max_n,
+            action_timeout,
+            scorer,
+            running_scorer: Cell::new(None),
         };
-        ctx.reload(config);
         ctx.reset();
         ctx
     }
@@ -56,7 +103,7 @@ impl Context {
     pub fn reload(&mut self, config: &Config) {
         self.action_items = actions::get_action_items(config)
             .into_iter()
-            .map(|x| Rc::new(x))
+            .map(Rc::new)
             .collect();
     }
 
@@ -65,6 +112,49 @@ impl Context {
         self.reference = None;
         self.list_items = self.action_items.clone();
         self.list_items.sort_by_key(|item| item.priority);
+        self.list_scorer = self.scorer;
+        self.marked_items.clear();
+    }
+
+    /// Mark or unmark item
+    pub fn toggle_marked(&mut self, item: &Rc<Item>) {
+        if let Some(idx) = self.marked_items.iter().position(|x| Rc::ptr_eq(x, item)) {
+            self.marked_items.remove(idx);
+        } else {
+            self.marked_items.push(item.clone());
+        }
+    }
+
+    pub fn is_marked(&self, item: &Item) -> bool {
+        self.marked_items
+            .iter()
+            .any(|x| std::ptr::eq(x.as_ref(), item))
+    }
+
+    /// Whether any of marked items is selectable
+    pub fn selectable_marked(&self) -> bool {
+        self.marked_items.iter().any(|item| self.selectable(item))
+    }
+
+    /// Combined reference of all marked items, one per line
+    pub fn marked_reference(&self) -> String {
+        self.marked_items
+            .iter()
+            .map(|item| item.data.as_ref().unwrap_or(&item.title).as_str())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    /// Quick-send all marked items together, as a combined reference
+    pub fn quicksend_marked(&mut self) -> Result<()> {
+        if self.marked_items.is_empty() {
+            bail!("No marked items");
+        }
+        let reference = self.marked_reference();
+        self.quicksend(&Item {
+            title: reference,
+            ..Item::default()
+        })
     }
 
     pub fn quicksend_from_clipboard(&mut self) -> Result<()> {
@@ -107,6 +197,35 @@ impl Context {
         }
     }
 
+    /// Item in list whose trigger keyword is `text` (case insensitive), to
+    /// run with the text typed after it as argument
+    pub fn find_trigger(&self, text: &str) -> Option<Rc<Item>> {
+        if text.is_empty() {
+            return None;
+        }
+        let text = text.to_lowercase();
+        self.list_items
+            .iter()
+            .find(|item| match item.trigger {
+                Some(ref trigger) => trigger.to_lowercase() == text,
+                None => false,
+            })
+            .filter(|item| self.selectable_with_text(item))
+            .cloned()
+    }
+
+    /// Split `text` like "g rust lifetimes" into the item triggered by its
+    /// first word and the argument after it
+    pub fn split_trigger<'a>(&self, text: &'a str) -> Option<(&'a str, Rc<Item>, &'a str)> {
+        let mut parts = text.trim_start().splitn(2, char::is_whitespace);
+        let keyword = parts.next()?;
+        let arg = parts.next()?.trim();
+        if arg.is_empty() {
+            return None;
+        }
+        self.find_trigger(keyword).map(|item| (keyword, item, arg))
+    }
+
     pub fn runnable_with_text_realtime(&self, item: &Item) -> bool {
         if let Some(ref action) = item.action {
             action.runnable_arg_realtime()
@@ -118,41 +237,220 @@ impl Context {
     pub fn async_select_callback(&mut self, items: Vec<Item>) {
         self.list_items = items.into_iter().map(|x| Rc::new(x)).collect();
         self.list_items.sort_by_key(|x| x.priority);
+        self.list_scorer = self.running_scorer.get().unwrap_or(self.scorer);
         self.reference = None;
+        self.marked_items.clear();
+    }
+
+    /// List all actions of `item` instead, for the action menu
+    pub fn list_actions(&mut self, item: &Item) {
+        self.list_items = item.action_items().into_iter().map(Rc::new).collect();
+        self.list_scorer = self.scorer;
+        self.marked_items.clear();
+    }
+
+    /// Merge items emitted by a running action into the list
+    pub fn async_select_batch(&mut self, items: Vec<Item>) {
+        self.list_items.extend(items.into_iter().map(Rc::new));
+        self.list_items.sort_by_key(|x| x.priority);
+    }
+
+    fn timeout_of(&self, action: &dyn Action) -> Option<Duration> {
+        action.timeout().or(self.action_timeout)
+    }
+
+    /// Run `run` in a new thread, wait for at most `timeout`
+    fn run_with_timeout<R>(handle: &CancelHandle, timeout: Duration, run: R) -> ActionResult
+    where
+        R: FnOnce() -> ActionResult + Send + 'static,
+    {
+        let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
+        let worker_handle = handle.clone();
+        thread::Builder::new()
+            .name(Uuid::new_v4().simple().to_string())
+            .spawn(move || {
+                cancel::set_current(Some(worker_handle));
+                // receiver may be gone if timed out
+                let _ = send_ch.send(run());
+            })?;
+        match recv_ch.recv_timeout(timeout) {
+            Ok(res) => res,
+            Err(mpsc::RecvTimeoutError::Timeout) => Err(ErrorKind::Timeout(timeout).into()),
+            Err(mpsc::RecvTimeoutError::Disconnected) => bail!("Action thread died"),
+        }
     }
 
-    pub fn async_select<F>(&self, item: &Item, callback: F) -> String
+    /// Run action in new thread with a new cancel handle,
+    /// call `callback` with the result unless it's cancelled.
+    /// If it does not finish within `timeout`, it's cancelled and
+    /// `callback` is called with `ErrorKind::Timeout`
+    fn spawn_action<R, F>(timeout: Option<Duration>, run: R, callback: F) -> CancelHandle
     where
+        R: FnOnce() -> ActionResult + Send + 'static,
         F: FnOnce(ActionResult) + Send + 'static,
+    {
+        let handle = CancelHandle::new();
+        let thread_handle = handle.clone();
+        thread::Builder::new()
+            .name(Uuid::new_v4().simple().to_string())
+            .spawn(move || {
+                let res = match timeout {
+                    Some(timeout) => Context::run_with_timeout(&thread_handle, timeout, run),
+                    None => {
+                        cancel::set_current(Some(thread_handle.clone()));
+                        run()
+                    }
+                };
+                if thread_handle.is_cancelled() {
+                    debug!("Action cancelled, drop result");
+                    return;
+                }
+                if let Err(Error(ErrorKind::Timeout(_), _)) = res {
+                    warn!("Action timed out, cancelling");
+                    thread_handle.cancel();
+                }
+                callback(res);
+            })
+            .unwrap();
+        handle
+    }
+
+    /// Same as `spawn_action`, but `run` may emit items while running,
+    /// which are passed to `callback` as `ActionEvent::Batch` before the final
+    /// `ActionEvent::Done` with all items
+    fn spawn_streaming_action<R, F>(timeout: Option<Duration>, run: R, callback: F) -> CancelHandle
+    where
+        R: FnOnce(&dyn Fn(Vec<Item>)) -> Result<()> + Send + 'static,
+        F: FnMut(ActionEvent) + Send + 'static,
+    {
+        let callback = Arc::new(Mutex::new(callback));
+        let batch_callback = callback.clone();
+        Context::spawn_action(
+            timeout,
+            move || {
+                let handle = cancel::current();
+                let emitted = Mutex::new(Vec::new());
+                run(&|items: Vec<Item>| {
+                    if handle.is_cancelled() || items.is_empty() {
+                        return;
+                    }
+                    emitted.lock().unwrap().extend(items.iter().cloned());
+                    (*batch_callback.lock().unwrap())(ActionEvent::Batch(items));
+                })?;
+                Ok(emitted.into_inner().unwrap())
+            },
+            move |res| (*callback.lock().unwrap())(ActionEvent::Done(res)),
+        )
+    }
+
+    /// Wrap `callback` to only receive the final result of a streaming action
+    fn done_callback<F>(callback: F) -> impl FnMut(ActionEvent) + Send + 'static
+    where
+        F: FnOnce(ActionResult) + Send + 'static,
+    {
+        let mut callback = Some(callback);
+        move |event| {
+            if let ActionEvent::Done(res) = event {
+                if let Some(callback) = callback.take() {
+                    callback(res);
+                }
+            }
+        }
+    }
+
+    pub fn async_select<F>(&self, item: &Item, callback: F) -> CancelHandle
+    where
+        F: FnOnce(ActionResult) + Send + 'static,
+    {
+        self.async_select_streaming(item, Context::done_callback(callback))
+    }
+
+    /// Select item, receiving items as soon as they are emitted by the action
+    pub fn async_select_streaming<F>(&self, item: &Item, callback: F) -> CancelHandle
+    where
+        F: FnMut(ActionEvent) + Send + 'static,
     {
         assert!(self.selectable(item));
-        let thread_uuid = Uuid::new_v4().simple().to_string();
         let action = item.action.clone().unwrap();
         let action_arg = self.reference.clone();
-        thread::Builder::new()
-            .name(thread_uuid.clone())
-            .spawn(move || {
-                let items = if let Some(arg) = action_arg {
-                    action.run_arg(&arg)
+        self.running_scorer.set(action.scorer());
+        Context::spawn_streaming_action(
+            self.timeout_of(action.as_ref()),
+            move |emit| {
+                let res = if let Some(arg) = action_arg {
+                    action.run_arg_streaming(&arg, emit)
                 } else {
-                    action.run_bare()
+                    action.run_bare_streaming(emit)
                 };
                 debug!("async select complete, calling back");
-                callback(items.chain_err(|| "Failed selecting item"));
-            })
-            .unwrap();
-        thread_uuid
+                res.chain_err(|| "Failed selecting item")
+            },
+            callback,
+        )
+    }
+
+    /// Select all marked items one by one (in a single background thread),
+    /// items from all of them are concatenated
+    pub fn async_select_marked<F>(&self, callback: F) -> CancelHandle
+    where
+        F: FnMut(ActionEvent) + Send + 'static,
+    {
+        let actions = self
+            .marked_items
+            .iter()
+            .filter(|item| self.selectable(item))
+            .map(|item| item.action.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert!(!actions.is_empty());
+        // run in sequence, so the timeout is the sum of all
+        let timeout = actions
+            .iter()
+            .map(|action| self.timeout_of(action.as_ref()))
+            .sum::<Option<Duration>>();
+        let action_arg = self.reference.clone();
+        self.running_scorer.set(None);
+        Context::spawn_streaming_action(
+            timeout,
+            move |emit| {
+                for action in actions.iter() {
+                    cancel::current().check()?;
+                    if let Some(ref arg) = action_arg {
+                        action.run_arg_streaming(arg, emit)
+                    } else {
+                        action.run_bare_streaming(emit)
+                    }
+                    .chain_err(|| "Failed selecting marked item")?;
+                }
+                debug!("async select marked complete, calling back");
+                Ok(())
+            },
+            callback,
+        )
     }
 
-    pub fn async_select_with_text<F>(&self, item: &Item, text: &str, callback: F) -> String
+    pub fn async_select_with_text<F>(&self, item: &Item, text: &str, callback: F) -> CancelHandle
     where
         F: FnOnce(ActionResult) + Send + 'static,
+    {
+        self.async_select_with_text_streaming(item, text, Context::done_callback(callback))
+    }
+
+    /// Select item with text, receiving items as soon as they are emitted
+    /// by the action
+    pub fn async_select_with_text_streaming<F>(
+        &self,
+        item: &Item,
+        text: &str,
+        callback: F,
+    ) -> CancelHandle
+    where
+        F: FnMut(ActionEvent) + Send + 'static,
     {
         assert!(self.selectable_with_text(&item));
 
         let text = text.to_string();
-        let thread_uuid = Uuid::new_v4().simple().to_string();
         let action = item.action.clone().unwrap();
+        self.running_scorer.set(action.scorer());
 
         if let Some(scope) = action.suggest_arg_scope() {
             if let Err(error) = self.lrudb.add(scope, &text, self.history_max_n) {
@@ -160,38 +458,42 @@ impl Context {
             }
         }
 
-        thread::Builder::new()
-            .name(thread_uuid.clone())
-            .spawn(move || {
-                let items = action.run_arg(&text);
+        Context::spawn_streaming_action(
+            self.timeout_of(action.as_ref()),
+            move |emit| {
+                let res = action.run_arg_streaming(&text, emit);
                 debug!("async select with text complete, calling back");
-                callback(items.chain_err(|| "Failed selecting item with text"));
-            })
-            .unwrap();
-        thread_uuid
+                res.chain_err(|| "Failed selecting item with text")
+            },
+            callback,
+        )
     }
 
-    pub fn async_run_with_text_realtime<F>(&self, item: &Item, text: &str, callback: F) -> String
+    pub fn async_run_with_text_realtime<F>(
+        &self,
+        item: &Item,
+        text: &str,
+        callback: F,
+    ) -> CancelHandle
     where
         F: FnOnce(ActionResult) + Send + 'static,
     {
         assert!(self.runnable_with_text_realtime(&item));
         let text = text.to_string();
-        let thread_uuid = Uuid::new_v4().simple().to_string();
         let action = item.action.clone().unwrap();
 
         let history_max_n = self.history_max_n;
         let lrudb = self.lrudb.clone();
-        thread::Builder::new()
-            .name(thread_uuid.clone())
-            .spawn(move || {
+        Context::spawn_action(
+            self.timeout_of(action.as_ref()),
+            move || {
                 let items = action.run_arg_realtime(&text);
                 let scope = action.suggest_arg_scope();
                 debug!("async run with realtime text complete, calling back");
                 if action.runnable_arg_realtime_is_suggestion() && items.is_ok() && scope.is_some()
                 {
                     // insert partial action with lrudb
-                    let items = items
+                    items
                         .unwrap()
                         .into_iter()
                         .map(|mut item| {
@@ -209,14 +511,13 @@ impl Context {
                             )));
                             Ok(item)
                         })
-                        .collect();
-                    callback(items);
+                        .collect()
                 } else {
-                    callback(items.chain_err(|| "Failed running arg in realtime"));
+                    items.chain_err(|| "Failed running arg in realtime")
                 }
-            })
-            .unwrap();
-        thread_uuid
+            },
+            callback,
+        )
     }
 
     pub fn quicksend(&mut self, item: &Item) -> Result<()> {
@@ -227,7 +528,9 @@ impl Context {
             .map(|x| x.clone())
             .collect();
         self.list_items.sort_by_key(|item| item.priority);
+        self.list_scorer = self.scorer;
         self.reference = Some(item.data.as_ref().unwrap_or(&item.title).clone());
+        self.marked_items.clear();
         Ok(())
     }
 
@@ -272,3 +575,37 @@ impl Context {
             .collect())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_action_timeout_test() {
+        let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
+        let handle = Context::spawn_action(
+            Some(Duration::from_millis(50)),
+            || {
+                let handle = cancel::current();
+                while !handle.is_cancelled() {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Ok(Vec::new())
+            },
+            move |res| send_ch.send(res).unwrap(),
+        );
+        match recv_ch.recv_timeout(Duration::from_secs(5)) {
+            Ok(Err(Error(ErrorKind::Timeout(_), _))) => (),
+            _ => panic!("Expected timeout"),
+        }
+        assert!(handle.is_cancelled());
+
+        let (send_ch, recv_ch) = mpsc::channel::<ActionResult>();
+        Context::spawn_action(
+            Some(Duration::from_secs(5)),
+            || Ok(vec![Item::default()]),
+            move |res| send_ch.send(res).unwrap(),
+        );
+        assert_eq!(recv_ch.recv().unwrap().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,208 @@
+// Encryption of LruDB data at rest
+//
+// Data is encrypted with AES-256-GCM-SIV, which stays secure when a nonce is
+// reused. A fixed nonce is used (with the scope as associated data), so that
+// the same data always encrypts to the same value and can still be
+// deduplicated and looked up in SQL. This only leaks whether two entries are
+// equal.
+
+extern crate aes_gcm_siv;
+extern crate hmac;
+extern crate pbkdf2;
+extern crate sha2;
+
+use self::{
+    aes_gcm_siv::{
+        aead::{Aead, KeyInit, Payload},
+        Aes256GcmSiv, Nonce,
+    },
+    hmac::{Hmac, Mac},
+    pbkdf2::pbkdf2_hmac,
+    sha2::Sha256,
+};
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::PathBuf,
+};
+
+use crate::mcore::{config::Config, errors::*};
+
+const KEY_LEN: usize = 32;
+const NONCE: [u8; 12] = [0; 12];
+pub const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// Source of the encryption key
+#[derive(Clone)]
+pub enum DbKey {
+    /// Key file, generated with random content if missing
+    KeyFile(PathBuf),
+    Passphrase(String),
+}
+
+impl DbKey {
+    /// Key configured by `core.db_encryption`, None if encryption is disabled
+    pub fn from_config(config: &Config) -> Result<Option<DbKey>> {
+        let mode = config
+            .get::<String>(&["core", "db_encryption"])
+            .chain_err(|| "Invalid db_encryption")?;
+        match mode.as_str() {
+            "none" => Ok(None),
+            "key_file" => Ok(Some(DbKey::KeyFile(
+                config
+                    .get_filename(&["core", "db_key_file"])
+                    .chain_err(|| "Invalid db_key_file")?,
+            ))),
+            "passphrase" => {
+                let env = config
+                    .get::<String>(&["core", "db_passphrase_env"])
+                    .chain_err(|| "Invalid db_passphrase_env")?;
+                match std::env::var(&env) {
+                    Ok(passphrase) if !passphrase.is_empty() => {
+                        Ok(Some(DbKey::Passphrase(passphrase)))
+                    }
+                    _ => bail!("Database passphrase is not set in ${}", env),
+                }
+            }
+            _ => bail!("Invalid db_encryption: {}", mode),
+        }
+    }
+
+    /// Read key file, generate one if missing
+    fn read_key_file(path: &PathBuf) -> io::Result<Vec<u8>> {
+        match File::open(path) {
+            Ok(mut file) => {
+                let mut content = Vec::new();
+                file.read_to_end(&mut content)?;
+                if content.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "Empty key file"));
+                }
+                Ok(content)
+            }
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => {
+                info!("Generating database key file {:?}", path);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let content = random_bytes(KEY_LEN)?;
+                OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .mode(0o600)
+                    .open(path)?
+                    .write_all(&content)?;
+                Ok(content)
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+pub fn random_bytes(len: usize) -> io::Result<Vec<u8>> {
+    let mut ret = vec![0; len];
+    File::open("/dev/urandom")?.read_exact(&mut ret)?;
+    Ok(ret)
+}
+
+pub fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|x| format!("{:02x}", x)).collect()
+}
+
+pub fn from_hex(text: &str) -> Option<Vec<u8>> {
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => Some((hex_digit(*high)? << 4) | hex_digit(*low)?),
+            _ => None,
+        })
+        .collect()
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|x| x as u8)
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; KEY_LEN] {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+pub struct DbCipher {
+    cipher: Aes256GcmSiv,
+}
+
+impl DbCipher {
+    /// Derive keys from `key` with `salt` (stored in database)
+    pub fn new(key: &DbKey, salt: &[u8]) -> io::Result<DbCipher> {
+        let master = match key {
+            DbKey::KeyFile(path) => hmac_sha256(salt, &DbKey::read_key_file(path)?),
+            DbKey::Passphrase(passphrase) => {
+                let mut master = [0; KEY_LEN];
+                pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, PBKDF2_ITERATIONS, &mut master);
+                master
+            }
+        };
+        let key = hmac_sha256(&master, b"minions lrudb encryption");
+        Ok(DbCipher {
+            cipher: Aes256GcmSiv::new(&key.into()),
+        })
+    }
+
+    /// Encrypt `data` in `scope`, as hex of ciphertext and tag
+    pub fn encrypt(&self, scope: &str, data: &str) -> String {
+        let payload = Payload {
+            msg: data.as_bytes(),
+            aad: scope.as_bytes(),
+        };
+        let output = self
+            .cipher
+            .encrypt(Nonce::from_slice(&NONCE), payload)
+            .expect("Data too long to encrypt");
+        to_hex(&output)
+    }
+
+    /// Decrypt value from `encrypt`, None if it's invalid (e.g. wrong key or scope)
+    pub fn decrypt(&self, scope: &str, text: &str) -> Option<String> {
+        let data = from_hex(text)?;
+        let payload = Payload {
+            msg: &data,
+            aad: scope.as_bytes(),
+        };
+        let output = self
+            .cipher
+            .decrypt(Nonce::from_slice(&NONCE), payload)
+            .ok()?;
+        String::from_utf8(output).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dbcipher_test() {
+        assert_eq!(from_hex(&to_hex(b"\x00\xffab")).unwrap(), b"\x00\xffab");
+        assert!(from_hex("abc").is_none());
+
+        let path = std::env::temp_dir().join(format!("minions-key-{}", std::process::id()));
+        let key = DbKey::KeyFile(path.clone());
+        let cipher = DbCipher::new(&key, b"salt").unwrap();
+        let encrypted = cipher.encrypt("test", "hello 世界");
+        assert_eq!(encrypted, cipher.encrypt("test", "hello 世界"));
+        assert_ne!(encrypted, cipher.encrypt("other", "hello 世界"));
+        assert_eq!(cipher.decrypt("test", &encrypted).unwrap(), "hello 世界");
+        assert!(cipher.decrypt("other", &encrypted).is_none());
+        assert!(cipher.decrypt("test", "abcd").is_none());
+
+        // same key file, different salt
+        let cipher = DbCipher::new(&key, b"pepper").unwrap();
+        assert!(cipher.decrypt("test", &encrypted).is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
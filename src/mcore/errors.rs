@@ -2,4 +2,15 @@ error_chain! {
     foreign_links {
         Io(::std::io::Error);
     }
+
+    errors {
+        Cancelled {
+            description("action cancelled")
+            display("Action cancelled")
+        }
+        Timeout(timeout: ::std::time::Duration) {
+            description("action timed out")
+            display("Action timed out after {:.1}s", timeout.as_secs_f64())
+        }
+    }
 }
@@ -3,144 +3,128 @@
 // @Last Modified by:   BlahGeek
 // @Last Modified time: 2018-03-21
 
-extern crate pinyin;
+use crate::mcore::transliterate::search_keys;
 
-use std::{collections::VecDeque, iter::Iterator, str::Chars};
-
-struct PinyinChars<'a> {
-    pyqueue: VecDeque<char>,
-    chars: Chars<'a>,
+/// Precomputed text to search in: chars of (transliterated) text, and their
+/// lowercase
+#[derive(Clone, Debug, Default)]
+pub struct SearchKey {
+    /// Chars along with the index (in chars) of original char in text
+    pub chars: Vec<(char, usize)>,
+    /// Lowercase of `chars`
+    pub lower: Vec<char>,
 }
 
-impl<'a> Iterator for PinyinChars<'a> {
-    type Item = char;
+impl SearchKey {
+    pub fn from_chars(chars: Vec<(char, usize)>) -> SearchKey {
+        let lower = chars.iter().map(|&(c, _)| fold_case(c, false)).collect();
+        SearchKey { chars, lower }
+    }
 
-    fn next(&mut self) -> Option<char> {
-        if let Some(c) = self.pyqueue.pop_front() {
-            return Some(c);
-        }
-        if let Some(c) = self.chars.next() {
-            if c.is_ascii() {
-                Some(c)
-            } else {
-                let mut s = String::new();
-                s.push(c);
-                for word in pinyin::lazy_pinyin(&s, &pinyin::Args::new()).into_iter() {
-                    for c in word.chars() {
-                        self.pyqueue.push_back(c)
-                    }
-                    self.pyqueue.push_back(' ')
-                }
-                Some(' ')
-            }
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    /// Char at `idx` to compare with (case folded) pattern
+    pub fn char_at(&self, idx: usize, casesensitive: bool) -> char {
+        if casesensitive {
+            self.chars[idx].0
         } else {
-            None
+            self.lower[idx]
         }
     }
 }
 
-impl<'a> PinyinChars<'a> {
-    fn new(s: &'a str) -> PinyinChars<'a> {
-        PinyinChars {
-            pyqueue: VecDeque::with_capacity(8),
-            chars: s.chars(),
-        }
+/// Lowercase of `c` if not case sensitive
+pub fn fold_case(c: char, casesensitive: bool) -> char {
+    if casesensitive {
+        c
+    } else {
+        c.to_lowercase().next().unwrap_or(c)
     }
 }
 
 pub fn fuzzymatch(text: &str, pattern: &str, casesensitive: bool) -> i32 {
-    if pattern.len() == 0 {
-        return 0;
+    fuzzymatch_positions(text, pattern, casesensitive).0
+}
+
+/// Same as `fuzzymatch`, also return indices (in chars) of matched chars in
+/// `text`, in increasing order. For transliterated matches, the index of the
+/// original character is returned.
+pub fn fuzzymatch_positions(text: &str, pattern: &str, casesensitive: bool) -> (i32, Vec<usize>) {
+    search_keys(text)
+        .iter()
+        .map(|key| fuzzymatch_key(key, pattern, casesensitive))
+        .max_by_key(|x| x.0)
+        .unwrap_or((0, Vec::new()))
+}
+
+/// Same as `fuzzymatch_positions`, using precomputed search key
+pub fn fuzzymatch_key(key: &SearchKey, pattern: &str, casesensitive: bool) -> (i32, Vec<usize>) {
+    if pattern.is_empty() {
+        return (0, Vec::new());
     }
 
-    let pinyin_chars = PinyinChars::new(text);
-    let mut text_iter = pinyin_chars.peekable();
-    let mut pattern_iter = pattern.chars();
+    let mut positions: Vec<usize> = Vec::new();
+    let mut text_idx = 0;
 
     let mut score = 0;
     let mut firstchar_bonus = 1;
     let mut noskip_bonus = 1;
 
-    let mut match_success = false;
     let mut last_text_ch: char = '\u{0}';
 
-    'outer: loop {
-        match pattern_iter.next() {
-            None => {
-                match_success = true;
-                break;
-            }
-            Some(pattern_ch) => {
-                let mut skipped_count = 0;
-                while let Some(text_ch) = text_iter.next() {
-                    if (casesensitive && text_ch != pattern_ch)
-                        || (!casesensitive
-                            && text_ch.to_lowercase().next() != pattern_ch.to_lowercase().next())
-                    {
-                        skipped_count += 1;
-                        noskip_bonus = 1;
-                        last_text_ch = text_ch;
-                    } else {
-                        score += 1;
-                        if skipped_count == 0 {
-                            score += noskip_bonus;
-                            noskip_bonus *= 2;
-                        }
-                        if text_ch.is_uppercase()
-                            || (text_ch.is_alphanumeric() && !last_text_ch.is_alphanumeric())
-                        {
-                            firstchar_bonus += 1;
-                        }
-                        last_text_ch = text_ch;
-                        continue 'outer;
-                    }
+    'outer: for pattern_ch in pattern.chars().map(|c| fold_case(c, casesensitive)) {
+        let mut skipped_count = 0;
+        while text_idx < key.len() {
+            let (text_ch, orig_idx) = key.chars[text_idx];
+            let matched = key.char_at(text_idx, casesensitive) == pattern_ch;
+            text_idx += 1;
+            if !matched {
+                skipped_count += 1;
+                noskip_bonus = 1;
+                last_text_ch = text_ch;
+            } else {
+                score += 1;
+                if positions.last() != Some(&orig_idx) {
+                    positions.push(orig_idx);
+                }
+                if skipped_count == 0 {
+                    score += noskip_bonus;
+                    noskip_bonus *= 2;
                 }
-                if let None = text_iter.peek() {
-                    break 'outer;
+                if text_ch.is_uppercase()
+                    || (text_ch.is_alphanumeric() && !last_text_ch.is_alphanumeric())
+                {
+                    firstchar_bonus += 1;
                 }
+                last_text_ch = text_ch;
+                continue 'outer;
             }
         }
+        return (0, Vec::new());
     }
 
-    if match_success {
-        score * firstchar_bonus
-    } else {
-        0
-    }
+    (score * firstchar_bonus, positions)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::vec::Vec;
 
     #[test]
-    fn fuzzymatch_test() {
-        assert!(fuzzymatch("hello world", "hw", false) > 0);
-        assert!(fuzzymatch("hello world", "hw", false) > fuzzymatch("hello world", "hl", false));
-        assert!(
-            fuzzymatch("hello world", "hell", false) > fuzzymatch("hello world", "hwld", false)
-        );
-        assert!(fuzzymatch("hello world", "hww", false) == 0);
-        assert!(fuzzymatch("Hello World", "hw", false) > 0);
-        assert!(fuzzymatch("Hello World", "hw", true) == 0);
-        assert!(
-            fuzzymatch("Hello World", "helloworld", false)
-                > fuzzymatch("Hello World", "hello", false)
-        );
-        assert!(fuzzymatch("Hello World", "world", false) > 0);
-        // assert!(fuzzymatch("你好 世界", "世界", false) > 0);
-        // assert!(fuzzymatch("你好 世界", "你世", false) > 0);
-        assert!(fuzzymatch("", "hw", false) == 0);
-    }
-
-    #[test]
-    fn pinyinchars_test() {
-        assert_eq!(
-            PinyinChars::new("你好 world").collect::<Vec<char>>(),
-            &[' ', 'n', 'i', ' ', ' ', 'h', 'a', 'o', ' ', ' ', 'w', 'o', 'r', 'l', 'd']
-        );
+    fn transliteration_test() {
         assert!(fuzzymatch("你好 世界", "nhsj", false) > 0);
         assert!(fuzzymatch("你好 世界", "ni", false) > 0);
+        assert!(fuzzymatch("你好 世界", "你世", false) > 0);
+        assert!(fuzzymatch("Café", "cafe", false) > 0);
+        assert_eq!(
+            fuzzymatch_positions("你好 world", "hw", false).1,
+            vec![1, 3]
+        );
     }
 }
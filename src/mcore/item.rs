@@ -14,6 +14,14 @@ pub enum Icon {
     FontAwesome(String),
 }
 
+/// A secondary action of an item, with its name
+#[derive(Clone)]
+pub struct Alternative {
+    /// Name of the action, e.g. "Copy path"
+    pub name: String,
+    pub action: Arc<dyn Action + Sync + Send>,
+}
+
 /// The item type (represents single selectable item (row))
 #[derive(Clone, Default)]
 pub struct Item {
@@ -33,9 +41,18 @@ pub struct Item {
 
     /// Search str, fallback to title
     pub search_str: Option<String>,
+    /// Extra words to match, e.g. tags or alias
+    pub keywords: Vec<String>,
+    /// Keyword to run the action with argument directly from the filter,
+    /// e.g. "g" to search "g rust lifetimes" with Google
+    pub trigger: Option<String>,
 
     /// Action, optional
     pub action: Option<Arc<dyn Action + Sync + Send>>,
+
+    /// Alternative actions, the first ones are bound to Ctrl+Enter and
+    /// Alt+Enter, all are listed in action menu
+    pub alternatives: Vec<Alternative>,
 }
 
 impl fmt::Display for Item {
@@ -61,4 +78,31 @@ impl Item {
             &self.title
         }
     }
+
+    /// Get item running the `idx`-th alternative action
+    pub fn alternative_item(&self, idx: usize) -> Option<Item> {
+        self.alternatives.get(idx).map(|alternative| Item {
+            title: alternative.name.clone(),
+            subtitle: Some(self.title.clone()),
+            icon: self.icon.clone(),
+            badge: Some("Action".into()),
+            data: self.data.clone(),
+            action: Some(alternative.action.clone()),
+            ..Item::default()
+        })
+    }
+
+    /// Get items for all actions of this item (primary action first),
+    /// for the action menu
+    pub fn action_items(&self) -> Vec<Item> {
+        let mut ret = Vec::new();
+        if self.action.is_some() {
+            ret.push(Item {
+                alternatives: Vec::new(),
+                ..self.clone()
+            });
+        }
+        ret.extend((0..self.alternatives.len()).filter_map(|idx| self.alternative_item(idx)));
+        ret
+    }
 }
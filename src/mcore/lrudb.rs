@@ -1,36 +1,277 @@
 extern crate chrono;
 extern crate rusqlite;
+extern crate serde_json;
 
-use self::{chrono::TimeZone, rusqlite::params};
+use self::{
+    chrono::TimeZone,
+    rusqlite::{params, OptionalExtension, TransactionBehavior},
+};
 
 use std::{path::Path, sync::Mutex};
 
+use crate::mcore::{
+    config::Config,
+    dbcipher::{self, DbCipher, DbKey},
+    errors::{self, ResultExt},
+};
+
+/// Optional metadata of an entry, stored as JSON (encrypted like data if enabled)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct LruMeta {
+    /// Application the data comes from, e.g. window class of clipboard owner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// MIME type of the data, e.g. "text/html"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Title of the action the data is entered for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub action: Option<String>,
+}
+
 pub struct LruResult {
     pub data: String,
     pub time: chrono::DateTime<chrono::Local>,
+    /// Number of times the data is added
+    pub count: i64,
+    /// Pinned entries are never dropped to keep last max_n entries
+    pub pinned: bool,
+    pub meta: Option<LruMeta>,
 }
 
+/// Database of recently used data, optionally with data encrypted at rest
 pub struct LruDB {
     conn: Mutex<rusqlite::Connection>,
+    cipher: Option<DbCipher>,
 }
 
 type Result<T> = ::std::result::Result<T, rusqlite::Error>;
 
+/// Schema migrations, the n-th one upgrades database from version n to n+1
+const MIGRATIONS: [&str; 3] = [
+    // initial schema, which may already exist in databases without version
+    "CREATE TABLE IF NOT EXISTS lrudata (
+        id INTEGER PRIMARY KEY,
+        scope TEXT,
+        data TEXT,
+        time INTEGER,
+        UNIQUE (scope, data)
+    );
+    CREATE INDEX IF NOT EXISTS scope_time_id_idx ON lrudata (scope, time, id);",
+    // use counts, pinned flag and metadata
+    "ALTER TABLE lrudata ADD COLUMN count INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE lrudata ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE lrudata ADD COLUMN meta TEXT;",
+    // salt and key verifier, exists only if data is encrypted
+    "CREATE TABLE encryption (salt TEXT NOT NULL, verifier TEXT NOT NULL);",
+];
+
+/// Scope and plaintext of the verifier, to detect wrong keys
+const VERIFIER_SCOPE: &str = "minions_verifier";
+const VERIFIER_DATA: &str = "minions";
+
+const SELECT_COLUMNS: &str = "SELECT data, time, count, pinned, meta FROM lrudata";
+
+/// Error about the encryption key, reported as a failure to convert data
+fn key_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(error.into())
+}
+
+/// Upgrade database to the latest schema version
+fn migrate(conn: &mut rusqlite::Connection) -> Result<()> {
+    // lock database, in case it's opened by others at the same time
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    tx.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)",
+        params![],
+    )?;
+    let version: i64 = tx.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_version",
+        params![],
+        |row| row.get(0),
+    )?;
+    if version as usize > MIGRATIONS.len() {
+        warn!(
+            "LRUDB schema version {} is newer than supported ({})",
+            version,
+            MIGRATIONS.len()
+        );
+        return tx.commit();
+    }
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        debug!("Migrating LRUDB to schema version {}", idx + 1);
+        tx.execute_batch(migration)?;
+    }
+    tx.execute("DELETE FROM schema_version", params![])?;
+    tx.execute(
+        "INSERT INTO schema_version (version) VALUES (?)",
+        params![MIGRATIONS.len() as i64],
+    )?;
+    tx.commit()
+}
+
+/// Set up encryption with `key`, encrypting existing data in place if the
+/// database is not encrypted yet
+fn setup_encryption(
+    conn: &mut rusqlite::Connection,
+    key: Option<&DbKey>,
+) -> Result<Option<DbCipher>> {
+    if key.is_some() {
+        // overwrite deleted content, so that no plaintext is left in free pages
+        conn.execute_batch("PRAGMA secure_delete = ON")?;
+    }
+    let mut encrypted_now = false;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let existing: Option<(String, String)> = tx
+        .query_row("SELECT salt, verifier FROM encryption", params![], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .optional()?;
+    let cipher = match (key, existing) {
+        (None, None) => None,
+        (None, Some(_)) => return Err(key_error("LRUDB is encrypted, but no key is configured")),
+        (Some(key), Some((salt, verifier))) => {
+            let salt = dbcipher::from_hex(&salt).ok_or_else(|| key_error("Invalid LRUDB salt"))?;
+            let cipher = DbCipher::new(key, &salt).map_err(key_error)?;
+            if cipher.decrypt(VERIFIER_SCOPE, &verifier).as_deref() != Some(VERIFIER_DATA) {
+                return Err(key_error("Wrong key for encrypted LRUDB"));
+            }
+            Some(cipher)
+        }
+        (Some(key), None) => {
+            let salt = dbcipher::random_bytes(dbcipher::SALT_LEN).map_err(key_error)?;
+            let cipher = DbCipher::new(key, &salt).map_err(key_error)?;
+            let entries = {
+                let mut stmt = tx.prepare("SELECT id, scope, data, meta FROM lrudata")?;
+                let rows = stmt.query_map(params![], |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                    ))
+                })?;
+                rows.collect::<Result<Vec<_>>>()?
+            };
+            info!("Encrypting {} existing LRUDB entries", entries.len());
+            for (id, scope, data, meta) in entries {
+                tx.execute(
+                    "UPDATE lrudata SET data = ?, meta = ? WHERE id = ?",
+                    params![
+                        &cipher.encrypt(&scope, &data),
+                        &meta.map(|meta| cipher.encrypt(&scope, &meta)),
+                        &id
+                    ],
+                )?;
+            }
+            tx.execute(
+                "INSERT INTO encryption (salt, verifier) VALUES (?, ?)",
+                params![
+                    &dbcipher::to_hex(&salt),
+                    &cipher.encrypt(VERIFIER_SCOPE, VERIFIER_DATA)
+                ],
+            )?;
+            encrypted_now = true;
+            Some(cipher)
+        }
+    };
+    tx.commit()?;
+    if encrypted_now {
+        // plaintext may still be in pages freed before secure_delete was on
+        conn.execute_batch("VACUUM")?;
+    }
+    Ok(cipher)
+}
+
 impl LruDB {
+    /// Data as stored in database, encrypted if enabled
+    fn encode(&self, scope: &str, s: &str) -> String {
+        match self.cipher {
+            Some(ref cipher) => cipher.encrypt(scope, s),
+            None => s.to_string(),
+        }
+    }
+
+    /// Metadata as stored in database, JSON encrypted like data
+    fn encode_meta(&self, scope: &str, meta: Option<&LruMeta>) -> Option<String> {
+        meta.map(|meta| self.encode(scope, &serde_json::to_string(meta).unwrap()))
+    }
+
+    fn read_row(&self, scope: &str, row: &rusqlite::Row) -> Result<LruResult> {
+        let data: String = row.get(0)?;
+        let data = match self.cipher {
+            Some(ref cipher) => cipher.decrypt(scope, &data).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Text,
+                    "Unable to decrypt LRUDB data".into(),
+                )
+            })?,
+            None => data,
+        };
+        let meta: Option<String> = row.get(4)?;
+        let meta = match self.cipher {
+            Some(ref cipher) => meta.and_then(|meta| {
+                let decrypted = cipher.decrypt(scope, &meta);
+                if decrypted.is_none() {
+                    warn!("Unable to decrypt metadata in LRUDB");
+                }
+                decrypted
+            }),
+            None => meta,
+        };
+        Ok(LruResult {
+            data,
+            time: chrono::Local.timestamp(row.get(1)?, 0),
+            count: row.get(2)?,
+            pinned: row.get(3)?,
+            meta: meta.and_then(|meta| match serde_json::from_str(&meta) {
+                Ok(meta) => Some(meta),
+                Err(error) => {
+                    warn!("Invalid metadata in LRUDB: {}", error);
+                    None
+                }
+            }),
+        })
+    }
+
     /// Add data at scope, keep last max_n entries
     pub fn add(&self, scope: &str, s: &str, max_n: i32) -> Result<()> {
+        self.add_with_meta(scope, s, None, max_n)
+    }
+
+    /// Add data at scope with metadata (replacing the previous one if any),
+    /// keep last max_n entries besides pinned ones
+    pub fn add_with_meta(
+        &self,
+        scope: &str,
+        s: &str,
+        meta: Option<&LruMeta>,
+        max_n: i32,
+    ) -> Result<()> {
         debug!("Adding `{}` to scope `{}`", s, scope);
+        let meta = self.encode_meta(scope, meta);
+        let s = self.encode(scope, s);
         let conn = self.conn.lock().unwrap();
         let now = chrono::Local::now().timestamp();
-        conn.execute(
-            "INSERT OR REPLACE INTO lrudata (scope, data, time) VALUES (?, ?, \
-			 ?)",
-            params![&scope, &s, &now],
+        // renew id as well, so that it's ordered first among entries added in the same second
+        let updated = conn.execute(
+            "UPDATE lrudata SET id = (SELECT MAX(id) FROM lrudata) + 1, time = ?,
+                      count = count + 1, meta = COALESCE(?, meta)
+                      WHERE scope = ? AND data = ?",
+            params![&now, &meta, &scope, &s],
         )?;
+        if updated == 0 {
+            conn.execute(
+                "INSERT INTO lrudata (scope, data, time, meta) VALUES (?, ?, ?, ?)",
+                params![&scope, &s, &now, &meta],
+            )?;
+        }
         conn.execute(
-            "DELETE FROM lrudata WHERE scope = ? AND id NOT IN
-                      (SELECT id FROM lrudata WHERE scope = ? ORDER BY time \
-			 DESC, id DESC LIMIT ?)",
+            "DELETE FROM lrudata WHERE scope = ? AND pinned = 0 AND id NOT IN
+                      (SELECT id FROM lrudata WHERE scope = ? AND pinned = 0
+                       ORDER BY time DESC, id DESC LIMIT ?)",
             params![&scope, &scope, &max_n],
         )?;
         Ok(())
@@ -39,54 +280,141 @@ impl LruDB {
     /// Get all data in order
     pub fn getall(&self, scope: &str) -> Result<Vec<LruResult>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT data, time FROM lrudata WHERE scope = ? ORDER BY time \
-			 DESC, id DESC",
-        )?;
-        let data_iter = stmt.query_map(&[&scope], |row| {
-            Ok(LruResult {
-                data: row.get(0)?,
-                time: chrono::Local.timestamp(row.get(1)?, 0),
-            })
-        })?;
-        let mut ret: Vec<LruResult> = Vec::new();
-        for data in data_iter {
-            ret.push(data.unwrap());
-        }
-        Ok(ret)
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE scope = ? ORDER BY time DESC, id DESC",
+            SELECT_COLUMNS
+        ))?;
+        let data_iter = stmt.query_map(&[&scope], |row| self.read_row(scope, row))?;
+        data_iter.collect()
     }
 
     pub fn getall_textonly(&self, scope: &str) -> Result<Vec<String>> {
         Ok(self.getall(scope)?.into_iter().map(|x| x.data).collect())
     }
 
+    /// Get all data, pinned ones first, then most used ones
+    pub fn getall_by_count(&self, scope: &str) -> Result<Vec<LruResult>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "{} WHERE scope = ? ORDER BY pinned DESC, count DESC, time DESC, id DESC",
+            SELECT_COLUMNS
+        ))?;
+        let data_iter = stmt.query_map(&[&scope], |row| self.read_row(scope, row))?;
+        data_iter.collect()
+    }
+
+    /// Get single entry
+    pub fn get(&self, scope: &str, s: &str) -> Result<Option<LruResult>> {
+        let s = self.encode(scope, s);
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!("{} WHERE scope = ? AND data = ?", SELECT_COLUMNS))?;
+        let mut data_iter = stmt.query_map(params![&scope, &s], |row| self.read_row(scope, row))?;
+        data_iter.next().transpose()
+    }
+
+    /// Pin or unpin entry, return false if not found
+    pub fn set_pinned(&self, scope: &str, s: &str, pinned: bool) -> Result<bool> {
+        let s = self.encode(scope, s);
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE lrudata SET pinned = ? WHERE scope = ? AND data = ?",
+            params![&pinned, &scope, &s],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Replace metadata of entry, return false if not found
+    pub fn set_meta(&self, scope: &str, s: &str, meta: Option<&LruMeta>) -> Result<bool> {
+        let meta = self.encode_meta(scope, meta);
+        let s = self.encode(scope, s);
+        let conn = self.conn.lock().unwrap();
+        let updated = conn.execute(
+            "UPDATE lrudata SET meta = ? WHERE scope = ? AND data = ?",
+            params![&meta, &scope, &s],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Remove entry (even if pinned), return false if not found
+    pub fn remove(&self, scope: &str, s: &str) -> Result<bool> {
+        let s = self.encode(scope, s);
+        let conn = self.conn.lock().unwrap();
+        let removed = conn.execute(
+            "DELETE FROM lrudata WHERE scope = ? AND data = ?",
+            params![&scope, &s],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Remove all entries in scope, except pinned ones if `keep_pinned`,
+    /// return number of removed entries
+    pub fn clear(&self, scope: &str, keep_pinned: bool) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM lrudata WHERE scope = ? AND (pinned = 0 OR NOT ?)",
+            params![&scope, &keep_pinned],
+        )
+    }
+
+    /// Remove entries in scope added before `time`, except pinned ones,
+    /// return number of removed entries
+    pub fn purge_before(
+        &self,
+        scope: &str,
+        time: chrono::DateTime<chrono::Local>,
+    ) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "DELETE FROM lrudata WHERE scope = ? AND pinned = 0 AND time < ?",
+            params![&scope, &time.timestamp()],
+        )
+    }
+
+    /// Current schema version of database
+    pub fn schema_version(&self) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT MAX(version) FROM schema_version",
+            params![],
+            |row| row.get(0),
+        )
+    }
+
+    /// Whether data is encrypted
+    pub fn encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
     pub fn new(dbpath: Option<&Path>) -> Result<LruDB> {
-        let conn = if let Some(dbpath) = dbpath {
+        LruDB::new_with_key(dbpath, None)
+    }
+
+    /// Open database, with data encrypted by `key` if provided.
+    /// Existing unencrypted database is encrypted in place
+    pub fn new_with_key(dbpath: Option<&Path>, key: Option<&DbKey>) -> Result<LruDB> {
+        let mut conn = if let Some(dbpath) = dbpath {
             rusqlite::Connection::open(dbpath)?
         } else {
             rusqlite::Connection::open_in_memory()?
         };
-
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS lrudata (
-                id INTEGER PRIMARY KEY,
-                scope TEXT,
-                data TEXT,
-                time INTEGER,
-                UNIQUE (scope, data)
-            )",
-            params![],
-        )?;
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS scope_time_id_idx ON lrudata (scope, \
-			 time, id)",
-            params![],
-        )?;
+        migrate(&mut conn)?;
+        let cipher = setup_encryption(&mut conn, key)?;
 
         Ok(LruDB {
             conn: Mutex::new(conn),
+            cipher,
         })
     }
+
+    /// Open `core.db_file`, with encryption configured in `core`
+    pub fn from_config(config: &Config) -> errors::Result<LruDB> {
+        let db_file = config
+            .get_filename(&["core", "db_file"])
+            .chain_err(|| "Invalid db_file")?;
+        let key = DbKey::from_config(config)?;
+        LruDB::new_with_key(Some(&db_file), key.as_ref())
+            .chain_err(|| format!("Unable to open LRUDB {:?}", db_file))
+    }
 }
 
 #[cfg(test)]
@@ -110,4 +438,123 @@ mod tests {
             vec!["2", "1", "hello"]
         );
     }
+
+    #[test]
+    fn lrudb_meta_test() {
+        let lru = LruDB::new(None).unwrap();
+        assert_eq!(lru.schema_version().unwrap(), MIGRATIONS.len() as i64);
+        let meta = LruMeta {
+            mime_type: Some("text/html".into()),
+            ..LruMeta::default()
+        };
+        lru.add_with_meta("test", "hello", Some(&meta), 2).unwrap();
+        lru.add("test", "hello", 2).unwrap();
+        let entry = lru.get("test", "hello").unwrap().unwrap();
+        assert_eq!(entry.count, 2);
+        assert_eq!(entry.meta, Some(meta));
+        assert!(lru.get("test", "world").unwrap().is_none());
+
+        // pinned entries are kept
+        assert!(lru.set_pinned("test", "hello", true).unwrap());
+        lru.add("test", "1", 2).unwrap();
+        lru.add("test", "2", 2).unwrap();
+        lru.add("test", "3", 2).unwrap();
+        assert_eq!(
+            lru.getall_textonly("test").unwrap(),
+            vec!["3", "2", "hello"]
+        );
+        assert_eq!(lru.getall_by_count("test").unwrap()[0].data, "hello");
+        let later = chrono::Local::now() + chrono::Duration::seconds(1);
+        assert_eq!(lru.purge_before("test", later).unwrap(), 2);
+        lru.add("test", "2", 2).unwrap();
+        lru.add("test", "3", 2).unwrap();
+
+        assert_eq!(lru.clear("test", true).unwrap(), 2);
+        assert!(lru.remove("test", "hello").unwrap());
+        assert!(!lru.remove("test", "hello").unwrap());
+    }
+
+    #[test]
+    fn lrudb_migrate_test() {
+        let path = std::env::temp_dir().join(format!("minions-lrudb-{}.db", std::process::id()));
+        {
+            // database created by old versions
+            let conn = rusqlite::Connection::open(&path).unwrap();
+            conn.execute_batch(MIGRATIONS[0]).unwrap();
+            conn.execute(
+                "INSERT INTO lrudata (scope, data, time) VALUES ('test', 'hello', 0)",
+                params![],
+            )
+            .unwrap();
+        }
+        let lru = LruDB::new(Some(&path)).unwrap();
+        assert_eq!(lru.schema_version().unwrap(), MIGRATIONS.len() as i64);
+        let entry = lru.get("test", "hello").unwrap().unwrap();
+        assert_eq!(entry.count, 1);
+        assert!(!entry.pinned);
+        assert!(entry.meta.is_none());
+        drop(lru);
+        // opened again without migrating
+        LruDB::new(Some(&path)).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn lrudb_encryption_test() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("minions-lrudb-encrypted-{}.db", std::process::id()));
+        let key_path = dir.join(format!("minions-lrudb-key-{}", std::process::id()));
+        let key = DbKey::KeyFile(key_path.clone());
+        let meta = |source: &str| LruMeta {
+            source: Some(source.into()),
+            ..LruMeta::default()
+        };
+        {
+            let lru = LruDB::new(Some(&path)).unwrap();
+            lru.add("test", "hello", 3).unwrap();
+            lru.add_with_meta("test", "world", Some(&meta("Firefox")), 3)
+                .unwrap();
+        }
+        // encrypt existing data in place
+        let lru = LruDB::new_with_key(Some(&path), Some(&key)).unwrap();
+        assert!(lru.encrypted());
+        let content = std::fs::read(&path).unwrap();
+        assert!(!content.windows(5).any(|x| x == b"hello" || x == b"world"));
+        assert!(!content.windows(7).any(|x| x == b"Firefox"));
+        lru.add_with_meta("test", "hello", Some(&meta("Terminal")), 3)
+            .unwrap();
+        assert_eq!(lru.getall_textonly("test").unwrap(), vec!["hello", "world"]);
+        let entry = lru.get("test", "hello").unwrap().unwrap();
+        assert_eq!(entry.count, 2);
+        assert_eq!(entry.meta, Some(meta("Terminal")));
+        let entry = lru.get("test", "world").unwrap().unwrap();
+        assert_eq!(entry.meta, Some(meta("Firefox")));
+        drop(lru);
+        let content = std::fs::read(&path).unwrap();
+        assert!(!content.windows(8).any(|x| x == b"Terminal"));
+
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        let stored: String = conn
+            .query_row("SELECT data FROM lrudata LIMIT 1", params![], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(stored != "hello" && stored != "world");
+        drop(conn);
+
+        assert!(LruDB::new(Some(&path)).is_err());
+        let wrong_key =
+            DbKey::KeyFile(dir.join(format!("minions-lrudb-key2-{}", std::process::id())));
+        assert!(LruDB::new_with_key(Some(&path), Some(&wrong_key)).is_err());
+        let lru = LruDB::new_with_key(Some(&path), Some(&key)).unwrap();
+        assert_eq!(lru.getall_textonly("test").unwrap(), vec!["hello", "world"]);
+
+        for file in &[path, key_path] {
+            std::fs::remove_file(file).unwrap();
+        }
+        if let DbKey::KeyFile(path) = wrong_key {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
 }
@@ -13,17 +13,30 @@ use self::byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::collections::btree_map::BTreeMap;
 
 use std::{
-    fs::{File, OpenOptions},
+    cell::RefCell,
+    fs::{self, File, OpenOptions},
     io,
     io::{Read, Write},
-    path::Path,
+    path::{Path, PathBuf},
     rc::Rc,
+    sync::{Arc, OnceLock},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use crate::mcore::{fuzzymatch::fuzzymatch, item::Item};
+use crate::mcore::{
+    cancel::CancelHandle,
+    config::Config,
+    errors::Result,
+    fuzzymatch::SearchKey,
+    item::Item,
+    reference::{ReferenceKind, ALL_KINDS},
+    scorer::{typo_distance, ScorerKind},
+    transliterate::{self, search_keys, Transliteration},
+};
 
 /// 20 byte array representing SHA1 hash result
-#[derive(PartialOrd, PartialEq, Eq, Ord, Debug)]
+#[derive(PartialOrd, PartialEq, Eq, Ord, Debug, Clone)]
 struct SHA1Result {
     bytes: [u8; 20],
 }
@@ -43,7 +56,25 @@ impl SHA1Result {
 
 impl<'a> From<&'a str> for SHA1Result {
     fn from(text: &'a str) -> SHA1Result {
+        PrefixHasher::new("").hash(text)
+    }
+}
+
+/// SHA1 hasher with some prefix already fed,
+/// to hash many texts sharing the same prefix
+#[derive(Clone, Copy)]
+struct PrefixHasher(Sha1);
+
+impl PrefixHasher {
+    fn new(prefix: &str) -> PrefixHasher {
         let mut hash = Sha1::new();
+        hash.input(prefix.as_bytes());
+        PrefixHasher(hash)
+    }
+
+    /// SHA1 of prefix + text
+    fn hash(&self, text: &str) -> SHA1Result {
+        let mut hash = self.0;
         hash.input(text.as_bytes());
 
         let mut bytes: [u8; 20] = [0; 20];
@@ -53,45 +84,474 @@ impl<'a> From<&'a str> for SHA1Result {
     }
 }
 
-const FILE_MAGIC: i32 = 0x23333333;
+/// Magic of legacy (v1) statistics file, with hit counts only
+const FILE_MAGIC_V1: i32 = 0x23333333;
+/// Magic of statistics file with time decayed weights
+const FILE_MAGIC: i32 = 0x23333334;
+
+/// Salt used in legacy statistics files
+const LEGACY_SALT: &str = "";
+
+/// Entries with weight below this are dropped when compacting
+const MIN_WEIGHT: f64 = 0.01;
+
+/// Time decayed hit count
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Frecency {
+    /// Weight at `time`
+    weight: f64,
+    /// Unix timestamp in seconds
+    time: i64,
+}
+
+impl Frecency {
+    fn read_from(reader: &mut dyn Read) -> io::Result<Frecency> {
+        Ok(Frecency {
+            weight: reader.read_f64::<LittleEndian>()?,
+            time: reader.read_i64::<LittleEndian>()?,
+        })
+    }
+
+    fn write_to(&self, writer: &mut dyn Write) -> io::Result<()> {
+        writer.write_f64::<LittleEndian>(self.weight)?;
+        writer.write_i64::<LittleEndian>(self.time)?;
+        Ok(())
+    }
+
+    /// Weight decayed to `now`, halved every `half_life` seconds
+    fn weight_at(&self, now: i64, half_life: Option<f64>) -> f64 {
+        match half_life {
+            Some(half_life) if now > self.time => {
+                self.weight * 0.5f64.powf((now - self.time) as f64 / half_life)
+            }
+            _ => self.weight,
+        }
+    }
+
+    /// Merge two records of the same hash
+    fn merge(&self, other: &Frecency, half_life: Option<f64>) -> Frecency {
+        let time = std::cmp::max(self.time, other.time);
+        Frecency {
+            weight: self.weight_at(time, half_life) + other.weight_at(time, half_life),
+            time,
+        }
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn insert(
+    map: &mut BTreeMap<SHA1Result, Frecency>,
+    sha1: SHA1Result,
+    frecency: Frecency,
+    half_life: Option<f64>,
+) -> Frecency {
+    let merged = match map.get(&sha1) {
+        Some(old) => old.merge(&frecency, half_life),
+        None => frecency,
+    };
+    map.insert(sha1, merged);
+    merged
+}
+
+/// Loaded statistics, shared with filtering threads (copied on write)
+#[derive(Clone)]
+struct History {
+    statistics: Arc<BTreeMap<SHA1Result, Frecency>>,
+    legacy: Arc<BTreeMap<SHA1Result, Frecency>>,
+    /// Half life of weight in seconds, None to disable decay
+    half_life: Option<f64>,
+}
+
+impl History {
+    /// Weight of hash at `now`, including legacy entries
+    fn weight(&self, now: i64, sha1: &SHA1Result, legacy_sha1: impl FnOnce() -> SHA1Result) -> f64 {
+        let mut weight = self
+            .statistics
+            .get(sha1)
+            .map(|x| x.weight_at(now, self.half_life))
+            .unwrap_or(0.0);
+        if !self.legacy.is_empty() {
+            if let Some(frecency) = self.legacy.get(&legacy_sha1()) {
+                weight += frecency.weight_at(now, self.half_life);
+            }
+        }
+        weight
+    }
+}
+
+/// Score from weight of statistics
+fn weight_score(weight: f64) -> i32 {
+    (weight + 1.0).log2() as i32
+}
+
+/// Weights of item fields when matching, 0 to disable matching the field
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct MatchWeights {
+    /// Title (or `search_str` if present)
+    pub title: f64,
+    pub keywords: f64,
+    pub subtitle: f64,
+    pub badge: f64,
+}
+
+impl Default for MatchWeights {
+    fn default() -> MatchWeights {
+        MatchWeights {
+            title: 1.0,
+            keywords: 0.8,
+            subtitle: 0.5,
+            badge: 0.3,
+        }
+    }
+}
+
+/// Content of statistics file
+struct Statistics {
+    /// SHA1 of the salt used by `entries`, None if unknown
+    salt_hash: Option<SHA1Result>,
+    /// Entries hashed with `LEGACY_SALT`, migrated from v1 file
+    legacy: BTreeMap<SHA1Result, Frecency>,
+    entries: BTreeMap<SHA1Result, Frecency>,
+}
+
+/// Texts of an item to match, copied from item to prepare in other threads
+struct ItemTexts {
+    /// Texts of fields with weights
+    fields: Vec<(String, f64)>,
+    search_str: String,
+}
+
+/// Precomputed search keys and statistics of an item
+struct ItemKey {
+    /// Search keys of fields with weights
+    fields: Vec<(Vec<SearchKey>, f64)>,
+    /// Score from statistics of the item, regardless of pattern
+    item_score: i32,
+}
+
+/// Items prepared for filtering, keys are computed on first use
+struct PreparedItems {
+    texts: Vec<ItemTexts>,
+    keys: OnceLock<Vec<ItemKey>>,
+}
+
+/// Items along with the prepared ones
+type PreparedList = (Vec<Rc<Item>>, Arc<PreparedItems>);
+
+/// Number of items scored in one thread at least
+const PARALLEL_CHUNK_SIZE: usize = 1024;
+
+/// Default max edit distance of typo tolerant matching
+const DEFAULT_TYPO_MAX_DISTANCE: usize = 2;
+
+/// Typo tolerant matching is used when strict matching yields fewer results
+const TYPO_FALLBACK_MIN_RESULTS: usize = 3;
+
+/// One edit is allowed for every this number of chars in pattern, so short
+/// patterns would not match everything
+const TYPO_CHARS_PER_EDIT: usize = 4;
+
+/// Map chunks of `items` in parallel, `f` is called with offset of chunk
+fn parallel_map<T, R, F>(items: &[T], f: F) -> Result<Vec<R>>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(usize, &[T]) -> Result<Vec<R>> + Sync,
+{
+    let threads = thread::available_parallelism()
+        .map(|x| x.get())
+        .unwrap_or(1);
+    let chunk_size = std::cmp::max(PARALLEL_CHUNK_SIZE, items.len().div_ceil(threads));
+    if items.len() <= chunk_size {
+        return f(0, items);
+    }
+    let f = &f;
+    let results = thread::scope(|scope| {
+        let handles = items
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(idx, chunk)| scope.spawn(move || f(idx * chunk_size, chunk)))
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Filtering thread panicked"))
+            .collect::<Result<Vec<Vec<R>>>>()
+    })?;
+    Ok(results.into_iter().flatten().collect())
+}
+
+/// Filtering items with a pattern, which can run in any thread
+pub struct SortTask {
+    prepared: Arc<PreparedItems>,
+    pattern: String,
+    scorer: ScorerKind,
+    typo_max_distance: usize,
+    history: History,
+    item_hasher: PrefixHasher,
+    legacy_item_hasher: PrefixHasher,
+    pattern_hasher: PrefixHasher,
+    legacy_pattern_hasher: PrefixHasher,
+    salt: String,
+    /// Hasher of entries of the kind of quick-send reference
+    reference_hasher: Option<PrefixHasher>,
+    cancel: Option<CancelHandle>,
+}
+
+impl SortTask {
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Number of items to filter
+    pub fn len(&self) -> usize {
+        self.prepared.texts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prepared.texts.is_empty()
+    }
+
+    /// Stop running (with `ErrorKind::Cancelled`) when `handle` is cancelled
+    pub fn with_cancel(mut self, handle: CancelHandle) -> SortTask {
+        self.cancel = Some(handle);
+        self
+    }
+
+    /// Also rank items by how often they are selected for the kind of
+    /// quick-send `reference`, if any
+    pub fn with_reference(mut self, reference: Option<&str>) -> SortTask {
+        self.reference_hasher =
+            reference.map(|x| Matcher::reference_hasher(&self.salt, ReferenceKind::of(x)));
+        self
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        match self.cancel {
+            Some(ref handle) => handle.check(),
+            None => Ok(()),
+        }
+    }
+
+    fn prepare(&self, texts: &[ItemTexts], now: i64) -> Vec<ItemKey> {
+        texts
+            .iter()
+            .map(|texts| {
+                let weight =
+                    self.history
+                        .weight(now, &self.item_hasher.hash(&texts.search_str), || {
+                            self.legacy_item_hasher.hash(&texts.search_str)
+                        });
+                ItemKey {
+                    fields: texts
+                        .fields
+                        .iter()
+                        .map(|(text, weight)| (search_keys(text), *weight))
+                        .collect(),
+                    item_score: weight_score(weight),
+                }
+            })
+            .collect()
+    }
+
+    /// (final score, index) of matched items in chunk starting at `offset`
+    fn score_chunk(&self, offset: usize, keys: &[ItemKey], now: i64) -> Result<Vec<(i32, usize)>> {
+        let scorer = self.scorer.scorer();
+        let mut ret = Vec::new();
+        for (idx, key) in keys.iter().enumerate() {
+            if idx % 256 == 0 {
+                self.check_cancelled()?;
+            }
+            // max weighted score of title, keywords, subtitle and badge
+            let p0 = key
+                .fields
+                .iter()
+                .map(|(field, weight)| {
+                    let score = scorer.score_keys(field, &self.pattern, false).0;
+                    (f64::from(score) * weight).ceil() as i32
+                })
+                .max()
+                .unwrap_or(0);
+            if p0 <= 0 {
+                continue;
+            }
+            let search_str = &self.prepared.texts[offset + idx].search_str;
+            let p1 = key.item_score;
+            let p2 = 2 * weight_score(self.history.weight(
+                now,
+                &self.pattern_hasher.hash(search_str),
+                || self.legacy_pattern_hasher.hash(search_str),
+            ));
+            let p3 = match self.reference_hasher {
+                Some(ref hasher) => {
+                    let sha1 = hasher.hash(search_str);
+                    2 * weight_score(self.history.weight(now, &sha1, || sha1.clone()))
+                }
+                None => 0,
+            };
+            trace!("Score: {}: {} + {} + {} + {}", search_str, p0, p1, p2, p3);
+            ret.push((p0 + p1 + p2 + p3, offset + idx));
+        }
+        Ok(ret)
+    }
+
+    /// (edit distance, item score, index) of items in chunk starting at
+    /// `offset` nearly matching the pattern, skipping `matched` ones (sorted)
+    fn typo_chunk(
+        &self,
+        offset: usize,
+        keys: &[ItemKey],
+        max_distance: usize,
+        matched: &[usize],
+    ) -> Result<Vec<(usize, i32, usize)>> {
+        let mut ret = Vec::new();
+        for (idx, key) in keys.iter().enumerate() {
+            if idx % 256 == 0 {
+                self.check_cancelled()?;
+            }
+            if matched.binary_search(&(offset + idx)).is_ok() {
+                continue;
+            }
+            let distance = key
+                .fields
+                .iter()
+                .filter(|(_, weight)| *weight > 0.0)
+                .flat_map(|(field, _)| field.iter())
+                .filter_map(|field| typo_distance(field, &self.pattern, max_distance))
+                .min();
+            if let Some(distance) = distance {
+                ret.push((distance, key.item_score, offset + idx));
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Indices of matched items, sorted by score
+    /// Items are prepared (on first run) and scored in multiple threads.
+    /// If few items match, items within max edit distance of the pattern are
+    /// appended, sorted by distance
+    pub fn run(&self) -> Result<Vec<usize>> {
+        let now = now();
+        let keys = self.prepared.keys.get_or_init(|| {
+            parallel_map(
+                &self.prepared.texts,
+                |_, texts| Ok(self.prepare(texts, now)),
+            )
+            .unwrap_or_default()
+        });
+        let mut scores = parallel_map(keys, |offset, keys| self.score_chunk(offset, keys, now))?;
+        scores.sort_by_key(|&(score, idx)| (-score, idx));
+        let mut indices: Vec<usize> = scores.into_iter().map(|(_, idx)| idx).collect();
+
+        let max_distance = self
+            .typo_max_distance
+            .min(self.pattern.chars().count() / TYPO_CHARS_PER_EDIT);
+        if indices.len() < TYPO_FALLBACK_MIN_RESULTS && max_distance > 0 {
+            let mut matched = indices.clone();
+            matched.sort_unstable();
+            let mut typos = parallel_map(keys, |offset, keys| {
+                self.typo_chunk(offset, keys, max_distance, &matched)
+            })?;
+            typos.sort_by_key(|&(distance, score, idx)| (distance, -score, idx));
+            trace!("Typo tolerant matches: {}", typos.len());
+            indices.extend(typos.into_iter().map(|(_, _, idx)| idx));
+        }
+        Ok(indices)
+    }
+}
 
 // Sort items using filter text, via fuzzymatch algorithm
 // Store select history in file to adjust future sorting
 //
 // for privacy reasons, original data should not be saved to file
-// instead, SHA1 hash and hit count is saved
+// instead, SHA1 hash and time decayed hit count (frecency) is saved
 //
 // for every hit: (filter_text, selected_item), two entries is inserted:
 //   - SHA1(SALT + selected_item)
 //   - SHA1(SALT + filter_text + selected_item)
+// and one more in quick-send mode, with the coarse kind of the reference
+// (URL, path, number...) instead of its content:
+//   - SHA1(SALT + @kind + selected_item)
+//
+// File format: MAGIC SHA1(SALT) LEGACY_COUNT
+//              (SHA1, weight, time) * LEGACY_COUNT
+//              (SHA1, weight, time) (SHA1, weight, time) ...
+// where legacy entries are migrated from v1 file (MAGIC (SHA1, count) ...),
+// which were hashed with empty salt.
+// At startup, the file is loaded, compacted (merge same hashes, drop decayed
+// ones) and dumped back to the file. While running, new data would be appended
+// only (would be compacted on next running)
 //
-// File format: MAGIC (SHA1, count) (SHA1, count) (SHA1, count) ...
-// At startup, the file is loaded, compacted (merge same hashes using count) and
-// dumped back to the file While running, new data would be appended only (would
-// be compacted on next running)
+// Search keys of items (pinyin and lowercase of texts, hashes) are computed
+// once for each list, and items are scored in multiple threads.
 pub struct Matcher {
-    statistics: BTreeMap<SHA1Result, u32>,
+    history: History,
     salt: String,
     file: io::BufWriter<File>,
+    weights: MatchWeights,
+    /// Max edit distance of typo tolerant fallback, 0 to disable
+    typo_max_distance: usize,
+    /// Items of last filtered list and their prepared keys
+    prepared: RefCell<Option<PreparedList>>,
 }
 
-fn dump(path: &Path, statistics: &BTreeMap<SHA1Result, u32>) -> io::Result<()> {
+fn dump(path: &Path, statistics: &Statistics) -> io::Result<()> {
     let f = File::create(path)?;
     let mut f = io::BufWriter::new(f);
 
     f.write_i32::<LittleEndian>(FILE_MAGIC)?;
-    for (sha1, count) in statistics.iter() {
+    match statistics.salt_hash {
+        Some(ref salt_hash) => salt_hash.write_to(&mut f)?,
+        None => SHA1Result { bytes: [0; 20] }.write_to(&mut f)?,
+    }
+    f.write_u32::<LittleEndian>(statistics.legacy.len() as u32)?;
+    for (sha1, frecency) in statistics.legacy.iter().chain(statistics.entries.iter()) {
         sha1.write_to(&mut f)?;
-        f.write_u32::<LittleEndian>(*count)?;
+        frecency.write_to(&mut f)?;
     }
-    Ok(())
+    f.flush()
 }
 
-fn load(path: &Path) -> io::Result<BTreeMap<SHA1Result, u32>> {
+fn load_v1(f: &mut dyn Read, time: i64) -> io::Result<Statistics> {
+    let mut legacy = BTreeMap::new();
+    while let Ok(sha1) = SHA1Result::read_from(f) {
+        let count = f.read_u32::<LittleEndian>()?;
+        let frecency = Frecency {
+            weight: f64::from(count),
+            time,
+        };
+        // no decay, the same as summing counts
+        insert(&mut legacy, sha1, frecency, None);
+    }
+    Ok(Statistics {
+        salt_hash: None,
+        legacy,
+        entries: BTreeMap::new(),
+    })
+}
+
+fn load(path: &Path, half_life: Option<f64>) -> io::Result<Statistics> {
     let f = File::open(path)?;
+    let mtime = f
+        .metadata()?
+        .modified()
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_secs() as i64)
+        .unwrap_or_else(now);
     let mut f = io::BufReader::new(f);
 
     let magic = f.read_i32::<LittleEndian>()?;
+    if magic == FILE_MAGIC_V1 {
+        info!("Migrating legacy statistics file {:?}", path);
+        return load_v1(&mut f, mtime);
+    }
     if magic != FILE_MAGIC {
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
@@ -99,124 +559,843 @@ fn load(path: &Path) -> io::Result<BTreeMap<SHA1Result, u32>> {
         ));
     }
 
-    let mut statistics = BTreeMap::new();
-    loop {
-        match SHA1Result::read_from(&mut f) {
-            Ok(sha1) => {
-                let count = f.read_u32::<LittleEndian>()?;
-                let mut inserted = false;
-                if let Some(val) = statistics.get_mut(&sha1) {
-                    *val += count;
-                    inserted = true;
-                }
-                if !inserted {
-                    statistics.insert(sha1, count);
-                }
-            }
-            Err(_) => {
-                break;
+    let salt_hash = SHA1Result::read_from(&mut f)?;
+    let legacy_count = f.read_u32::<LittleEndian>()?;
+    let mut statistics = Statistics {
+        salt_hash: if salt_hash.bytes == [0; 20] {
+            None
+        } else {
+            Some(salt_hash)
+        },
+        legacy: BTreeMap::new(),
+        entries: BTreeMap::new(),
+    };
+    for _ in 0..legacy_count {
+        let sha1 = SHA1Result::read_from(&mut f)?;
+        let frecency = Frecency::read_from(&mut f)?;
+        insert(&mut statistics.legacy, sha1, frecency, half_life);
+    }
+    // appended entries may be truncated
+    while let Ok(sha1) = SHA1Result::read_from(&mut f) {
+        match Frecency::read_from(&mut f) {
+            Ok(frecency) => {
+                insert(&mut statistics.entries, sha1, frecency, half_life);
             }
+            Err(_) => break,
         }
     }
 
     Ok(statistics)
 }
 
+/// Location, salt and half life of statistics in global (core) config
+fn statistics_config(global_config: &Config) -> io::Result<(PathBuf, String, Option<Duration>)> {
+    let path = global_config
+        .get_filename(&["statistic_file"])
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let salt = global_config
+        .get::<String>(&["statistic_file_salt"])
+        .unwrap_or_default();
+    let half_life = match global_config.get::<u64>(&["statistic_half_life"]) {
+        Ok(0) => None,
+        Ok(days) => Some(Duration::from_secs(days * 24 * 3600)),
+        Err(_) => None,
+    };
+    Ok((path, salt, half_life))
+}
+
+/// Patterns longer than this are not tried when forgetting an item
+const FORGET_PATTERN_MAX_LEN: usize = 16;
+/// Only initials of the first words are tried when forgetting an item
+const FORGET_INITIALS_MAX_LEN: usize = 10;
+
+/// Summary of statistics file, see `StatisticsFile::summary`
+pub struct StatisticsSummary {
+    /// Number of entries
+    pub entries: usize,
+    /// Number of entries migrated from legacy (v1) file
+    pub legacy: usize,
+    /// Number of entries to be dropped by compacting
+    pub decayed: usize,
+    /// Sum of weights at now, roughly the number of recent hits (doubled,
+    /// as hits with filter text are recorded twice)
+    pub total_weight: f64,
+    /// Unix timestamp of the last hit
+    pub last_time: Option<i64>,
+    /// Whether entries are hashed with the configured salt
+    pub salt_matched: bool,
+}
+
+/// Statistics file opened for management (`minions stats`), changes are
+/// written by `save`. As only hashes are stored, items can only be found
+/// by their exact titles.
+pub struct StatisticsFile {
+    path: PathBuf,
+    salt: String,
+    half_life: Option<f64>,
+    statistics: Statistics,
+}
+
+impl StatisticsFile {
+    /// Open statistics file with settings in global (core) config
+    pub fn from_config(global_config: &Config) -> io::Result<StatisticsFile> {
+        let (path, salt, half_life) = statistics_config(global_config)?;
+        StatisticsFile::open(&path, &salt, half_life)
+    }
+
+    /// Open statistics file at `path`, an empty one if not exists
+    pub fn open(
+        path: &Path,
+        salt: &str,
+        half_life: Option<Duration>,
+    ) -> io::Result<StatisticsFile> {
+        let half_life = half_life.map(|x| x.as_secs_f64());
+        let statistics = if path.exists() {
+            load(path, half_life)?
+        } else {
+            Statistics {
+                salt_hash: Some(SHA1Result::from(salt)),
+                legacy: BTreeMap::new(),
+                entries: BTreeMap::new(),
+            }
+        };
+        Ok(StatisticsFile {
+            path: path.to_path_buf(),
+            salt: salt.into(),
+            half_life,
+            statistics,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn summary(&self) -> StatisticsSummary {
+        let now = now();
+        let statistics = &self.statistics;
+        let all = || {
+            statistics
+                .entries
+                .values()
+                .chain(statistics.legacy.values())
+        };
+        StatisticsSummary {
+            entries: statistics.entries.len(),
+            legacy: statistics.legacy.len(),
+            decayed: all()
+                .filter(|x| x.weight_at(now, self.half_life) < MIN_WEIGHT)
+                .count(),
+            total_weight: all().map(|x| x.weight_at(now, self.half_life)).sum(),
+            last_time: all().map(|x| x.time).max(),
+            salt_matched: statistics.entries.is_empty()
+                || statistics.salt_hash == Some(SHA1Result::from(self.salt.as_str())),
+        }
+    }
+
+    /// Drop decayed entries (and entries hashed with another salt), return
+    /// number of dropped entries. The same is done when Minions starts
+    pub fn compact(&mut self) -> usize {
+        let salt_hash = SHA1Result::from(self.salt.as_str());
+        let mut dropped = 0;
+        if self.statistics.salt_hash.as_ref() != Some(&salt_hash) {
+            dropped += self.statistics.entries.len();
+            self.statistics.entries.clear();
+        }
+        self.statistics.salt_hash = Some(salt_hash);
+
+        let now = now();
+        let half_life = self.half_life;
+        for map in [&mut self.statistics.legacy, &mut self.statistics.entries].iter_mut() {
+            let len = map.len();
+            map.retain(|_, frecency| frecency.weight_at(now, half_life) >= MIN_WEIGHT);
+            dropped += len - map.len();
+        }
+        dropped
+    }
+
+    /// Drop all entries
+    pub fn reset(&mut self) {
+        self.statistics.entries.clear();
+        self.statistics.legacy.clear();
+        self.statistics.salt_hash = Some(SHA1Result::from(self.salt.as_str()));
+    }
+
+    /// Drop entries of item with `title` (or search text), and entries of it
+    /// with given filter `patterns`, with substrings of the title or with
+    /// initials of its words as pattern (as they are likely typed). Other
+    /// fuzzy patterns (e.g. "gglchr") can not be guessed from the hashes and
+    /// must be given. Return number of dropped entries.
+    pub fn forget(&mut self, title: &str, patterns: &[&str]) -> usize {
+        let chars = title
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect::<Vec<char>>();
+        let mut candidates = patterns
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<String>>();
+        for start in 0..chars.len() {
+            for end in start + 1..=std::cmp::min(chars.len(), start + FORGET_PATTERN_MAX_LEN) {
+                let pattern = chars[start..end].iter().collect::<String>();
+                candidates.push(pattern.to_lowercase());
+                candidates.push(pattern);
+            }
+        }
+        let initials = title
+            .split(|c: char| !c.is_alphanumeric())
+            .filter_map(|word| word.chars().next())
+            .take(FORGET_INITIALS_MAX_LEN)
+            .collect::<Vec<char>>();
+        for mask in 1..1usize << initials.len() {
+            let pattern = initials
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| mask & (1 << idx) != 0)
+                .map(|(_, c)| c)
+                .collect::<String>();
+            candidates.push(pattern.to_lowercase());
+            candidates.push(pattern);
+        }
+        candidates.sort();
+        candidates.dedup();
+
+        let mut dropped = 0;
+        for (map, salt) in [
+            (&mut self.statistics.entries, self.salt.as_str()),
+            (&mut self.statistics.legacy, LEGACY_SALT),
+        ]
+        .iter_mut()
+        {
+            let len = map.len();
+            map.remove(&Matcher::item_hasher(salt).hash(title));
+            for pattern in candidates.iter() {
+                map.remove(&Matcher::pattern_hasher(salt, pattern).hash(title));
+            }
+            for kind in ALL_KINDS.iter() {
+                map.remove(&Matcher::reference_hasher(salt, *kind).hash(title));
+            }
+            dropped += len - map.len();
+        }
+        dropped
+    }
+
+    /// Write statistics to another file, e.g. to be merged on other machine
+    pub fn export(&self, path: &Path) -> io::Result<()> {
+        dump(path, &self.statistics)
+    }
+
+    /// Load statistics file at `path`, which must use the same salt
+    fn load_other(&self, path: &Path) -> io::Result<Statistics> {
+        let other = load(path, self.half_life)?;
+        if !other.entries.is_empty()
+            && other.salt_hash != Some(SHA1Result::from(self.salt.as_str()))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Statistics file is hashed with another salt",
+            ));
+        }
+        Ok(other)
+    }
+
+    /// Replace statistics with file at `path`
+    pub fn import(&mut self, path: &Path) -> io::Result<()> {
+        self.statistics = self.load_other(path)?;
+        self.statistics.salt_hash = Some(SHA1Result::from(self.salt.as_str()));
+        Ok(())
+    }
+
+    /// Add statistics in file at `path`, return number of merged entries
+    pub fn merge(&mut self, path: &Path) -> io::Result<usize> {
+        let other = self.load_other(path)?;
+        if self.statistics.salt_hash != Some(SHA1Result::from(self.salt.as_str())) {
+            // hashed with another salt, useless anyway
+            self.statistics.entries.clear();
+            self.statistics.salt_hash = Some(SHA1Result::from(self.salt.as_str()));
+        }
+        let count = other.entries.len() + other.legacy.len();
+        for (sha1, frecency) in other.entries {
+            insert(&mut self.statistics.entries, sha1, frecency, self.half_life);
+        }
+        for (sha1, frecency) in other.legacy {
+            insert(&mut self.statistics.legacy, sha1, frecency, self.half_life);
+        }
+        Ok(count)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        dump(&self.path, &self.statistics)
+    }
+}
+
 impl Matcher {
-    fn hash_item(&self, item: &Item) -> SHA1Result {
-        let s = format!("{}:{}", &self.salt, item.get_search_str());
-        s.as_str().into()
+    fn item_hasher(salt: &str) -> PrefixHasher {
+        PrefixHasher::new(&format!("{}:", salt))
+    }
+
+    fn pattern_hasher(salt: &str, pattern: &str) -> PrefixHasher {
+        PrefixHasher::new(&format!("{}:{}:", salt, pattern))
     }
 
-    fn hash_pattern_item(&self, pattern: &str, item: &Item) -> SHA1Result {
-        let s = format!("{}:{}:{}", &self.salt, pattern, item.get_search_str());
-        s.as_str().into()
+    fn reference_hasher(salt: &str, kind: ReferenceKind) -> PrefixHasher {
+        // "@" is never in filter text, so never collides with pattern entries
+        PrefixHasher::new(&format!("{}:@{}:", salt, kind.name()))
     }
 
-    fn inc(&mut self, sha1: SHA1Result) -> io::Result<u32> {
+    fn hash_item(salt: &str, item: &Item) -> SHA1Result {
+        Matcher::item_hasher(salt).hash(item.get_search_str())
+    }
+
+    fn hash_pattern_item(salt: &str, pattern: &str, item: &Item) -> SHA1Result {
+        Matcher::pattern_hasher(salt, pattern).hash(item.get_search_str())
+    }
+
+    #[cfg(test)]
+    fn item_weight(&self, item: &Item) -> f64 {
+        self.history
+            .weight(now(), &Matcher::hash_item(&self.salt, item), || {
+                Matcher::hash_item(LEGACY_SALT, item)
+            })
+    }
+
+    #[cfg(test)]
+    fn pattern_item_weight(&self, pattern: &str, item: &Item) -> f64 {
+        self.history.weight(
+            now(),
+            &Matcher::hash_pattern_item(&self.salt, pattern, item),
+            || Matcher::hash_pattern_item(LEGACY_SALT, pattern, item),
+        )
+    }
+
+    fn inc(&mut self, sha1: SHA1Result) -> io::Result<f64> {
         trace!("Inc: {:?}", &sha1);
 
+        let frecency = Frecency {
+            weight: 1.0,
+            time: now(),
+        };
         sha1.write_to(&mut self.file)?;
-        self.file.write_u32::<LittleEndian>(1)?;
+        frecency.write_to(&mut self.file)?;
         self.file.flush()?;
 
-        if let Some(val) = self.statistics.get_mut(&sha1) {
-            *val += 1;
-            return Ok(*val);
-        }
-        self.statistics.insert(sha1, 1);
-        Ok(1)
+        let statistics = Arc::make_mut(&mut self.history.statistics);
+        let merged = insert(statistics, sha1, frecency, self.history.half_life);
+        Ok(merged.weight)
     }
 
-    /// Record a hit of item, optionally with pattern
+    /// Record a hit of item, optionally with pattern and quick-send reference
+    /// (only its kind is recorded)
     /// This would update the statistics and append log to file
-    pub fn record(&mut self, pattern: Option<&str>, item: &Item) -> io::Result<()> {
+    pub fn record(
+        &mut self,
+        pattern: Option<&str>,
+        reference: Option<&str>,
+        item: &Item,
+    ) -> io::Result<()> {
         trace!(
             "Record history with pattern {:?} and item {}",
             pattern,
             item.get_search_str()
         );
-        let sha1 = self.hash_item(item);
-        let count = self.inc(sha1)?;
-        trace!("New count for item: {}", count);
+        // item scores are changed
+        *self.prepared.borrow_mut() = None;
+
+        let sha1 = Matcher::hash_item(&self.salt, item);
+        let weight = self.inc(sha1)?;
+        trace!("New weight for item: {}", weight);
         if let Some(pattern) = pattern {
             if pattern.len() > 0 {
-                let sha1 = self.hash_pattern_item(pattern, item);
-                let count = self.inc(sha1)?;
-                trace!("New count for item with pattern: {}", count);
+                let sha1 = Matcher::hash_pattern_item(&self.salt, pattern, item);
+                let weight = self.inc(sha1)?;
+                trace!("New weight for item with pattern: {}", weight);
             }
         }
+        if let Some(reference) = reference {
+            let kind = ReferenceKind::of(reference);
+            let sha1 = Matcher::reference_hasher(&self.salt, kind).hash(item.get_search_str());
+            let weight = self.inc(sha1)?;
+            trace!("New weight for item with {:?} reference: {}", kind, weight);
+        }
         Ok(())
     }
 
-    pub fn new(path: &Path, salt: &str) -> io::Result<Matcher> {
-        let statistics = if path.exists() {
+    /// Items sorted by how often they are selected for the kind of quick-send
+    /// `reference`, in original order if never selected
+    pub fn rank_for_reference(&self, reference: &str, items: &[Rc<Item>]) -> Vec<Rc<Item>> {
+        let now = now();
+        let hasher = Matcher::reference_hasher(&self.salt, ReferenceKind::of(reference));
+        let mut ranked = items
+            .iter()
+            .map(|item| {
+                let sha1 = hasher.hash(item.get_search_str());
+                let score = weight_score(self.history.weight(now, &sha1, || sha1.clone()));
+                (score, item.clone())
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by_key(|x| -x.0);
+        ranked.into_iter().map(|x| x.1).collect()
+    }
+
+    /// Open statistics file with settings in global (core) config
+    pub fn from_config(global_config: &Config) -> io::Result<Matcher> {
+        let (path, salt, half_life) = statistics_config(global_config)?;
+        let mut matcher = Matcher::new(&path, &salt, half_life)?;
+        matcher.weights = global_config
+            .get::<MatchWeights>(&["match_weights"])
+            .unwrap_or_default();
+        matcher.typo_max_distance = global_config
+            .get::<usize>(&["typo_max_distance"])
+            .unwrap_or(DEFAULT_TYPO_MAX_DISTANCE);
+        transliterate::configure(
+            global_config
+                .get::<Transliteration>(&["transliteration"])
+                .unwrap_or_default(),
+        );
+        Ok(matcher)
+    }
+
+    /// Open statistics file at `path`, migrating it from legacy format if
+    /// required. Weights are halved every `half_life`, None to disable decay
+    pub fn new(path: &Path, salt: &str, half_life: Option<Duration>) -> io::Result<Matcher> {
+        let half_life = half_life.map(|x| x.as_secs_f64());
+        let salt_hash = SHA1Result::from(salt);
+        let mut statistics = if path.exists() {
             debug!("Opening {:?} for statistics", path);
-            load(path)?
+            load(path, half_life)?
         } else {
             debug!("Using empty statistics");
-            BTreeMap::new()
+            Statistics {
+                salt_hash: None,
+                legacy: BTreeMap::new(),
+                entries: BTreeMap::new(),
+            }
         };
-        trace!("Loaded statistics: {}", statistics.len());
+        if statistics.salt_hash.as_ref() != Some(&salt_hash) && !statistics.entries.is_empty() {
+            warn!("Statistic file salt changed, previous statistics are dropped");
+            statistics.entries.clear();
+        }
+        statistics.salt_hash = Some(salt_hash);
+
+        let now = now();
+        for map in [&mut statistics.legacy, &mut statistics.entries].iter_mut() {
+            map.retain(|_, frecency| frecency.weight_at(now, half_life) >= MIN_WEIGHT);
+        }
+        trace!(
+            "Loaded statistics: {} (legacy: {})",
+            statistics.entries.len(),
+            statistics.legacy.len()
+        );
 
         debug!("Dump (compact) statistics to {:?}", path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
         dump(path, &statistics)?;
 
         Ok(Matcher {
-            statistics,
+            history: History {
+                statistics: Arc::new(statistics.entries),
+                legacy: Arc::new(statistics.legacy),
+                half_life,
+            },
             file: io::BufWriter::new(OpenOptions::new().append(true).open(path)?),
             salt: salt.into(),
+            weights: MatchWeights::default(),
+            typo_max_distance: DEFAULT_TYPO_MAX_DISTANCE,
+            prepared: RefCell::new(None),
         })
     }
 
-    /// Filter and sort items using fuzzymatch
-    /// return filtered items
-    pub fn sort(&self, pattern: &str, items: &[Rc<Item>]) -> Vec<Rc<Item>> {
-        trace!("filter: {:?}", pattern);
-        let scores = items.iter().map(|item| {
-            let p0 = fuzzymatch(item.get_search_str(), pattern, false);
-            let p1 = ((self.statistics.get(&self.hash_item(&item)).unwrap_or(&0) + 1) as f32).log2()
-                as i32;
-            let p2 = 2
-                * ((self
-                    .statistics
-                    .get(&self.hash_pattern_item(pattern, &item))
-                    .unwrap_or(&0)
-                    + 1) as f32)
-                    .log2() as i32;
-            trace!("Score: {}: {} + {} + {}", &item.title, p0, p1, p2);
-            (p0 + p1 + p2, p0) // final score and the base score
+    fn item_texts(&self, item: &Item) -> ItemTexts {
+        let weights = &self.weights;
+        let fields = std::iter::once((item.get_search_str(), weights.title))
+            .chain(
+                item.keywords
+                    .iter()
+                    .chain(item.trigger.iter())
+                    .map(|x| (x.as_str(), weights.keywords)),
+            )
+            .chain(item.subtitle.iter().map(|x| (x.as_str(), weights.subtitle)))
+            .chain(item.badge.iter().map(|x| (x.as_str(), weights.badge)))
+            .filter(|&(text, weight)| weight > 0.0 && !text.is_empty())
+            .map(|(text, weight)| (text.to_string(), weight))
+            .collect();
+        ItemTexts {
+            fields,
+            search_str: item.get_search_str().into(),
+        }
+    }
+
+    /// Prepared items, reused if `items` is the same as last time
+    fn prepare(&self, items: &[Rc<Item>]) -> Arc<PreparedItems> {
+        let mut prepared = self.prepared.borrow_mut();
+        if let Some((ref prepared_items, ref prepared)) = *prepared {
+            if prepared_items.len() == items.len()
+                && prepared_items
+                    .iter()
+                    .zip(items.iter())
+                    .all(|(x, y)| Rc::ptr_eq(x, y))
+            {
+                return prepared.clone();
+            }
+        }
+        trace!("Preparing {} items for filtering", items.len());
+        let ret = Arc::new(PreparedItems {
+            texts: items.iter().map(|item| self.item_texts(item)).collect(),
+            keys: OnceLock::new(),
         });
-        let mut items_and_scores = items
+        *prepared = Some((items.to_vec(), ret.clone()));
+        ret
+    }
+
+    /// Task to filter and sort items using given scorer,
+    /// see `SortTask::run` and `resolve`
+    pub fn sort_task(&self, pattern: &str, items: &[Rc<Item>], scorer: ScorerKind) -> SortTask {
+        trace!("filter: {:?} using {:?}", pattern, scorer);
+        SortTask {
+            prepared: self.prepare(items),
+            pattern: pattern.into(),
+            scorer,
+            typo_max_distance: self.typo_max_distance,
+            history: self.history.clone(),
+            item_hasher: Matcher::item_hasher(&self.salt),
+            legacy_item_hasher: Matcher::item_hasher(LEGACY_SALT),
+            pattern_hasher: Matcher::pattern_hasher(&self.salt, pattern),
+            legacy_pattern_hasher: Matcher::pattern_hasher(LEGACY_SALT, pattern),
+            salt: self.salt.clone(),
+            reference_hasher: None,
+            cancel: None,
+        }
+    }
+
+    /// Items from result of `task`,
+    /// None if the list is changed after the task is created
+    pub fn resolve(&self, task: &SortTask, indices: &[usize]) -> Option<Vec<Rc<Item>>> {
+        match *self.prepared.borrow() {
+            Some((ref items, ref prepared)) if Arc::ptr_eq(prepared, &task.prepared) => {
+                Some(indices.iter().map(|&idx| items[idx].clone()).collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// Filter and sort items using given scorer
+    /// return filtered items
+    pub fn sort(&self, pattern: &str, items: &[Rc<Item>], scorer: ScorerKind) -> Vec<Rc<Item>> {
+        let task = self.sort_task(pattern, items, scorer);
+        match task.run() {
+            Ok(indices) => indices.iter().map(|&idx| items[idx].clone()).collect(),
+            Err(error) => {
+                warn!("Unable to filter items: {}", error);
+                Vec::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frecency_test() {
+        let half_life = Some(10.0);
+        let x = Frecency {
+            weight: 4.0,
+            time: 100,
+        };
+        assert_eq!(x.weight_at(120, half_life), 1.0);
+        assert_eq!(x.weight_at(120, None), 4.0);
+        assert_eq!(x.weight_at(90, half_life), 4.0);
+        let y = Frecency {
+            weight: 1.0,
+            time: 110,
+        };
+        assert_eq!(
+            x.merge(&y, half_life),
+            Frecency {
+                weight: 3.0,
+                time: 110
+            }
+        );
+    }
+
+    #[test]
+    fn matcher_test() {
+        let path = std::env::temp_dir().join(format!("minions-matcher-{}.dat", std::process::id()));
+        let item = Item {
+            title: "hello".into(),
+            ..Item::default()
+        };
+
+        // legacy file, hit twice with pattern "h"
+        {
+            let mut f = File::create(&path).unwrap();
+            f.write_i32::<LittleEndian>(FILE_MAGIC_V1).unwrap();
+            for sha1 in [
+                Matcher::hash_item(LEGACY_SALT, &item),
+                Matcher::hash_pattern_item(LEGACY_SALT, "h", &item),
+            ]
             .iter()
-            .map(|x| x.clone())
-            .zip(scores.into_iter())
-            .collect::<Vec<(Rc<Item>, (i32, i32))>>();
-        items_and_scores.sort_by_key(|item_and_score| -(item_and_score.1).0);
-        items_and_scores
-            .into_iter()
-            .filter(|item_and_score| (item_and_score.1).1 > 0)
-            .map(|item_and_score| item_and_score.0)
-            .collect::<Vec<Rc<Item>>>()
+            {
+                sha1.write_to(&mut f).unwrap();
+                f.write_u32::<LittleEndian>(2).unwrap();
+            }
+        }
+        let half_life = Some(Duration::from_secs(3600));
+        let mut matcher = Matcher::new(&path, "salt", half_life).unwrap();
+        assert!((matcher.item_weight(&item) - 2.0).abs() < 0.01);
+        assert!((matcher.pattern_item_weight("h", &item) - 2.0).abs() < 0.01);
+
+        matcher.record(Some("h"), None, &item).unwrap();
+        drop(matcher);
+        let matcher = Matcher::new(&path, "salt", half_life).unwrap();
+        assert!((matcher.item_weight(&item) - 3.0).abs() < 0.01);
+        assert!((matcher.pattern_item_weight("h", &item) - 3.0).abs() < 0.01);
+        assert_eq!(matcher.pattern_item_weight("x", &item), 0.0);
+        drop(matcher);
+
+        // changing salt drops new entries, but not legacy ones
+        let matcher = Matcher::new(&path, "other", half_life).unwrap();
+        assert!((matcher.item_weight(&item) - 2.0).abs() < 0.01);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn statistics_file_test() {
+        let path = std::env::temp_dir().join(format!("minions-stats-{}.dat", std::process::id()));
+        let other =
+            std::env::temp_dir().join(format!("minions-stats-{}.export", std::process::id()));
+        let google = Item {
+            title: "Google".into(),
+            ..Item::default()
+        };
+        let bing = Item {
+            title: "Bing".into(),
+            ..Item::default()
+        };
+        let chrome = Item {
+            title: "Google Chrome".into(),
+            ..Item::default()
+        };
+        {
+            let mut matcher = Matcher::new(&path, "salt", None).unwrap();
+            matcher.record(Some("goo"), None, &google).unwrap();
+            matcher.record(Some("b"), None, &bing).unwrap();
+            matcher.record(Some("gc"), None, &chrome).unwrap();
+        }
+
+        let mut stats = StatisticsFile::open(&path, "salt", None).unwrap();
+        let summary = stats.summary();
+        assert_eq!(summary.entries, 6);
+        assert!((summary.total_weight - 6.0).abs() < 0.01);
+        assert!(summary.salt_matched);
+        assert!(
+            !StatisticsFile::open(&path, "other", None)
+                .unwrap()
+                .summary()
+                .salt_matched
+        );
+
+        // substrings of title are tried as pattern
+        assert_eq!(stats.forget("Google", &[]), 2);
+        assert_eq!(stats.forget("Google", &[]), 0);
+        // and so are initials of words
+        assert_eq!(stats.forget("Google Chrome", &[]), 2);
+        stats.export(&other).unwrap();
+        stats.merge(&other).unwrap();
+        stats.save().unwrap();
+        let matcher = Matcher::new(&path, "salt", None).unwrap();
+        assert_eq!(matcher.item_weight(&google), 0.0);
+        assert!((matcher.pattern_item_weight("b", &bing) - 2.0).abs() < 0.01);
+        drop(matcher);
+
+        let mut stats = StatisticsFile::open(&path, "other", None).unwrap();
+        assert!(stats.merge(&other).is_err());
+        assert!(stats.import(&other).is_err());
+        stats.reset();
+        assert_eq!(stats.summary().entries, 0);
+
+        let mut stats = StatisticsFile::open(&path, "salt", None).unwrap();
+        stats.reset();
+        stats.import(&other).unwrap();
+        assert_eq!(stats.summary().entries, 2);
+        assert_eq!(stats.compact(), 0);
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&other).unwrap();
+    }
+
+    #[test]
+    fn reference_test() {
+        let path = std::env::temp_dir().join(format!("minions-ref-{}.dat", std::process::id()));
+        let mut matcher = Matcher::new(&path, "salt", None).unwrap();
+        let items = ["Search", "Open", "Calculator"]
+            .iter()
+            .map(|title| {
+                Rc::new(Item {
+                    title: title.to_string(),
+                    ..Item::default()
+                })
+            })
+            .collect::<Vec<_>>();
+        let titles = |res: Vec<Rc<Item>>| res.iter().map(|x| x.title.clone()).collect::<Vec<_>>();
+
+        matcher
+            .record(None, Some("https://example.com"), &items[1])
+            .unwrap();
+        matcher.record(Some(""), Some("1+1"), &items[2]).unwrap();
+        assert_eq!(
+            titles(matcher.rank_for_reference("https://rust-lang.org", &items)),
+            vec!["Open", "Search", "Calculator"]
+        );
+        assert_eq!(
+            titles(matcher.rank_for_reference("2+2", &items)),
+            vec!["Calculator", "Search", "Open"]
+        );
+        assert_eq!(
+            titles(matcher.rank_for_reference("some text", &items)),
+            vec!["Search", "Open", "Calculator"]
+        );
+
+        matcher.record(None, Some("some text"), &items[0]).unwrap();
+        let sort = |reference| {
+            let task = matcher
+                .sort_task("r", &items, ScorerKind::Fuzzy)
+                .with_reference(Some(reference));
+            let indices = task.run().unwrap();
+            titles(matcher.resolve(&task, &indices).unwrap())
+        };
+        assert_eq!(sort("3*4"), vec!["Calculator", "Search"]);
+        assert_eq!(sort("other text"), vec!["Search", "Calculator"]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn weights_test() {
+        let path = std::env::temp_dir().join(format!("minions-weights-{}.dat", std::process::id()));
+        let matcher = Matcher::new(&path, "salt", None).unwrap();
+        let items = vec![
+            Rc::new(Item {
+                title: "Terminal".into(),
+                keywords: vec!["shell".into()],
+                ..Item::default()
+            }),
+            Rc::new(Item {
+                title: "Files".into(),
+                subtitle: Some("Browse shell scripts".into()),
+                ..Item::default()
+            }),
+            Rc::new(Item {
+                title: "Shell".into(),
+                ..Item::default()
+            }),
+            Rc::new(Item {
+                title: "Editor".into(),
+                badge: Some("Desktop Entry".into()),
+                trigger: Some("vi".into()),
+                ..Item::default()
+            }),
+        ];
+        let titles = |pattern| {
+            matcher
+                .sort(pattern, &items, ScorerKind::Fuzzy)
+                .iter()
+                .map(|x| x.title.clone())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(titles("shell"), vec!["Shell", "Terminal", "Files"]);
+        assert_eq!(titles("desktop"), vec!["Editor"]);
+        assert_eq!(titles("vi"), vec!["Editor"]);
+        assert!(titles("xyz").is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn typo_test() {
+        let path = std::env::temp_dir().join(format!("minions-typo-{}.dat", std::process::id()));
+        let mut matcher = Matcher::new(&path, "salt", None).unwrap();
+        let items = ["Google Chrome", "Gogs", "Firefox"]
+            .iter()
+            .map(|title| {
+                Rc::new(Item {
+                    title: title.to_string(),
+                    ..Item::default()
+                })
+            })
+            .collect::<Vec<_>>();
+        let titles = |res: Vec<Rc<Item>>| res.iter().map(|x| x.title.clone()).collect::<Vec<_>>();
+
+        // near misses are ranked below strict matches
+        assert_eq!(
+            titles(matcher.sort("gogole", &items, ScorerKind::Fuzzy)),
+            vec!["Google Chrome"]
+        );
+        assert_eq!(
+            titles(matcher.sort("gogs", &items, ScorerKind::Fuzzy)),
+            vec!["Gogs"]
+        );
+        assert_eq!(
+            titles(matcher.sort("firefxo", &items, ScorerKind::Prefix)),
+            vec!["Firefox"]
+        );
+        // too short to tolerate typos
+        assert!(matcher.sort("gx", &items, ScorerKind::Fuzzy).is_empty());
+
+        matcher.typo_max_distance = 0;
+        assert!(matcher.sort("gogole", &items, ScorerKind::Fuzzy).is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sort_task_test() {
+        let path = std::env::temp_dir().join(format!("minions-sort-{}.dat", std::process::id()));
+        let mut matcher = Matcher::new(&path, "salt", None).unwrap();
+        // strict matching only, see typo_test
+        matcher.typo_max_distance = 0;
+        // large enough to be scored in multiple threads
+        let items = (0..5000)
+            .map(|idx| {
+                Rc::new(Item {
+                    title: format!("item {}", idx),
+                    ..Item::default()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        matcher.record(Some("4999"), None, &items[4999]).unwrap();
+        let res = matcher.sort("4999", &items, ScorerKind::Substring);
+        assert_eq!(res.len(), 1);
+        assert!(Rc::ptr_eq(&res[0], &items[4999]));
+        // ranked by statistics, then by order in list
+        let res = matcher.sort("item", &items, ScorerKind::Fuzzy);
+        assert_eq!(res.len(), 5000);
+        assert_eq!(res[0].title, "item 4999");
+        assert_eq!(res[1].title, "item 0");
+
+        let task = matcher.sort_task("item 1", &items, ScorerKind::Prefix);
+        let indices = task.run().unwrap();
+        assert_eq!(indices.len(), 1111);
+        assert_eq!(matcher.resolve(&task, &indices).unwrap()[0].title, "item 1");
+        // list changed
+        matcher.sort("item", &items[1..], ScorerKind::Fuzzy);
+        assert!(matcher.resolve(&task, &indices).is_none());
+
+        let handle = CancelHandle::new();
+        let task = matcher
+            .sort_task("item", &items, ScorerKind::Fuzzy)
+            .with_cancel(handle.clone());
+        handle.cancel();
+        assert!(task.run().is_err());
+
+        fs::remove_file(&path).unwrap();
     }
 }
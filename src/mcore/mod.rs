@@ -4,10 +4,15 @@
 // @Last Modified time: 2018-04-08
 
 pub mod action;
+pub mod cancel;
 pub mod config;
 pub mod context;
+pub mod dbcipher;
 pub mod errors;
 pub mod fuzzymatch;
 pub mod item;
 pub mod lrudb;
 pub mod matcher;
+pub mod reference;
+pub mod scorer;
+pub mod transliterate;
@@ -0,0 +1,116 @@
+// Coarse kinds of quick-send references
+//
+// Only the kind of a reference (not its content) is used to rank actions in
+// quick-send mode, see `Matcher::rank_for_reference`.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReferenceKind {
+    Url,
+    /// File path, absolute or relative to home
+    Path,
+    /// Number or arithmetic expression
+    Number,
+    /// Single word (no whitespace)
+    Word,
+    /// Text with multiple lines
+    MultiLine,
+    /// Text in non-Latin script, e.g. Chinese or Russian
+    NonLatin,
+    /// Other single line text
+    Text,
+}
+
+pub const ALL_KINDS: [ReferenceKind; 7] = [
+    ReferenceKind::Url,
+    ReferenceKind::Path,
+    ReferenceKind::Number,
+    ReferenceKind::Word,
+    ReferenceKind::MultiLine,
+    ReferenceKind::NonLatin,
+    ReferenceKind::Text,
+];
+
+/// Whether `c` is a letter in Latin script (including ones with diacritics)
+fn is_latin(c: char) -> bool {
+    c.is_ascii()
+        || matches!(c,
+            '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' | '\u{2C60}'..='\u{2C7F}'
+            | '\u{A720}'..='\u{A7FF}')
+}
+
+/// Whether `text` starts with an URL scheme, e.g. "https://"
+fn has_scheme(text: &str) -> bool {
+    match text.find("://") {
+        Some(idx) if idx > 0 => {
+            let scheme = &text[..idx];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+        }
+        _ => false,
+    }
+}
+
+impl ReferenceKind {
+    pub fn of(text: &str) -> ReferenceKind {
+        let text = text.trim();
+        let single_word = !text.contains(char::is_whitespace);
+        if text.contains('\n') {
+            ReferenceKind::MultiLine
+        } else if single_word
+            && (has_scheme(text) || text.starts_with("www.") || text.starts_with("mailto:"))
+        {
+            ReferenceKind::Url
+        } else if text.starts_with('/') || text.starts_with("~/") {
+            ReferenceKind::Path
+        } else if text.contains(|c: char| c.is_ascii_digit())
+            && text
+                .chars()
+                .all(|c| c.is_ascii_digit() || c.is_whitespace() || "+-*/^%().,=xe".contains(c))
+        {
+            ReferenceKind::Number
+        } else if text.chars().any(|c| c.is_alphabetic() && !is_latin(c)) {
+            ReferenceKind::NonLatin
+        } else if single_word {
+            ReferenceKind::Word
+        } else {
+            ReferenceKind::Text
+        }
+    }
+
+    /// Name of the kind, hashed in statistics
+    pub fn name(self) -> &'static str {
+        match self {
+            ReferenceKind::Url => "url",
+            ReferenceKind::Path => "path",
+            ReferenceKind::Number => "number",
+            ReferenceKind::Word => "word",
+            ReferenceKind::MultiLine => "multiline",
+            ReferenceKind::NonLatin => "nonlatin",
+            ReferenceKind::Text => "text",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_kind_test() {
+        let kind = ReferenceKind::of;
+        assert_eq!(kind("https://example.com/a?b=c"), ReferenceKind::Url);
+        assert_eq!(kind("  www.example.com\n"), ReferenceKind::Url);
+        assert_eq!(kind("/home/user/some file.txt"), ReferenceKind::Path);
+        assert_eq!(kind("~/notes"), ReferenceKind::Path);
+        assert_eq!(kind("2+2"), ReferenceKind::Number);
+        assert_eq!(kind("3.14 * (2 - 1)"), ReferenceKind::Number);
+        assert_eq!(kind("lifetimes"), ReferenceKind::Word);
+        assert_eq!(kind("Café"), ReferenceKind::Word);
+        assert_eq!(kind("rust lifetimes"), ReferenceKind::Text);
+        assert_eq!(kind("first\nsecond"), ReferenceKind::MultiLine);
+        assert_eq!(kind("你好 世界"), ReferenceKind::NonLatin);
+        assert_eq!(kind("Москва"), ReferenceKind::NonLatin);
+    }
+}
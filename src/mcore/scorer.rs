@@ -0,0 +1,453 @@
+// Scoring algorithms used to filter items
+//
+// All scorers search in the same keys as `fuzzymatch` (transliterated and
+// original text, see `transliterate`), so they behave consistently for
+// non-ascii text.
+// The default scorer is selected by `[core] scorer` in config, an action may
+// request another one for its result list via `Action::scorer`.
+
+use crate::mcore::{
+    fuzzymatch::{fold_case, fuzzymatch_key, SearchKey},
+    transliterate::search_keys,
+};
+
+/// Scoring algorithm of text against filter pattern
+pub trait Scorer: Sync {
+    /// Score of text (as precomputed `key`) matching `pattern` (0 if not
+    /// matched), and indices (in chars) of matched chars in text, in
+    /// increasing order
+    fn score_key(&self, key: &SearchKey, pattern: &str, casesensitive: bool) -> (i32, Vec<usize>);
+
+    /// Best score of text matching `pattern` in any of its `keys`
+    fn score_keys(
+        &self,
+        keys: &[SearchKey],
+        pattern: &str,
+        casesensitive: bool,
+    ) -> (i32, Vec<usize>) {
+        keys.iter()
+            .map(|key| self.score_key(key, pattern, casesensitive))
+            .max_by_key(|x| x.0)
+            .unwrap_or((0, Vec::new()))
+    }
+
+    fn score_positions(&self, text: &str, pattern: &str, casesensitive: bool) -> (i32, Vec<usize>) {
+        self.score_keys(&search_keys(text), pattern, casesensitive)
+    }
+
+    fn score(&self, text: &str, pattern: &str, casesensitive: bool) -> i32 {
+        self.score_positions(text, pattern, casesensitive).0
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScorerKind {
+    /// Greedy fuzzy match, see `fuzzymatch`
+    #[default]
+    Fuzzy,
+    /// fzf-style fuzzy match, find the best alignment
+    Fzf,
+    /// Pattern must be a prefix of the text or of some word in it
+    Prefix,
+    /// Pattern must be a substring of the text
+    Substring,
+}
+
+impl ScorerKind {
+    pub fn scorer(self) -> &'static dyn Scorer {
+        match self {
+            ScorerKind::Fuzzy => &FuzzyScorer,
+            ScorerKind::Fzf => &FzfScorer,
+            ScorerKind::Prefix => &PrefixScorer,
+            ScorerKind::Substring => &SubstringScorer,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CharClass {
+    Lower,
+    Upper,
+    Digit,
+    Letter,
+    NonWord,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_lowercase() {
+        CharClass::Lower
+    } else if c.is_uppercase() {
+        CharClass::Upper
+    } else if c.is_numeric() {
+        CharClass::Digit
+    } else if c.is_alphabetic() {
+        CharClass::Letter
+    } else {
+        CharClass::NonWord
+    }
+}
+
+/// Whether a word starts at `idx`
+fn is_boundary(chars: &[(char, usize)], idx: usize) -> bool {
+    let cur = char_class(chars[idx].0);
+    if idx == 0 {
+        return cur != CharClass::NonWord;
+    }
+    let prev = char_class(chars[idx - 1].0);
+    (prev == CharClass::NonWord && cur != CharClass::NonWord)
+        || (prev == CharClass::Lower && cur == CharClass::Upper)
+        || (prev != CharClass::Digit && cur == CharClass::Digit)
+}
+
+/// Indices of original chars from indices in `chars`, deduplicated
+fn original_positions(chars: &[(char, usize)], indices: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut positions: Vec<usize> = Vec::new();
+    for idx in indices {
+        let orig = chars[idx].1;
+        if positions.last() != Some(&orig) {
+            positions.push(orig);
+        }
+    }
+    positions
+}
+
+/// Pattern chars to compare with `SearchKey::char_at`
+fn pattern_chars(pattern: &str, casesensitive: bool) -> Vec<char> {
+    pattern
+        .chars()
+        .map(|c| fold_case(c, casesensitive))
+        .collect()
+}
+
+/// Start indices in `key` where `pattern` matches contiguously
+fn find_runs<'a>(
+    key: &'a SearchKey,
+    pattern: &'a [char],
+    casesensitive: bool,
+) -> impl Iterator<Item = usize> + 'a {
+    let len = (key.len() + 1).saturating_sub(pattern.len());
+    (0..len).filter(move |&start| {
+        pattern
+            .iter()
+            .enumerate()
+            .all(|(i, &p)| key.char_at(start + i, casesensitive) == p)
+    })
+}
+
+pub struct FuzzyScorer;
+
+impl Scorer for FuzzyScorer {
+    fn score_key(&self, key: &SearchKey, pattern: &str, casesensitive: bool) -> (i32, Vec<usize>) {
+        fuzzymatch_key(key, pattern, casesensitive)
+    }
+}
+
+const SCORE_MATCH: i32 = 16;
+const SCORE_GAP_START: i32 = -3;
+const SCORE_GAP_EXTENSION: i32 = -1;
+const BONUS_BOUNDARY: i32 = 8;
+const BONUS_CAMEL: i32 = 7;
+const BONUS_CONSECUTIVE: i32 = -(SCORE_GAP_START + SCORE_GAP_EXTENSION);
+const BONUS_FIRST_CHAR_MULTIPLIER: i32 = 2;
+const SCORE_NONE: i32 = i32::MIN / 4;
+
+fn fzf_bonus(chars: &[(char, usize)], idx: usize) -> i32 {
+    if !is_boundary(chars, idx) {
+        0
+    } else if idx == 0 || char_class(chars[idx - 1].0) == CharClass::NonWord {
+        BONUS_BOUNDARY
+    } else {
+        BONUS_CAMEL
+    }
+}
+
+/// Similar to fzf's algorithm (v2): find the alignment with max score using
+/// dynamic programming, where matches at word boundaries and consecutive
+/// matches get bonus and gaps get penalty
+pub struct FzfScorer;
+
+impl Scorer for FzfScorer {
+    fn score_key(&self, key: &SearchKey, pattern: &str, casesensitive: bool) -> (i32, Vec<usize>) {
+        let pattern = pattern_chars(pattern, casesensitive);
+        if pattern.is_empty() {
+            return (0, Vec::new());
+        }
+        let chars = &key.chars;
+        let (n, m) = (chars.len(), pattern.len());
+        // quick check before the costly one
+        let mut remaining = pattern.iter().peekable();
+        for j in 0..n {
+            if remaining.peek() == Some(&&key.char_at(j, casesensitive)) {
+                remaining.next();
+            }
+        }
+        if remaining.peek().is_some() {
+            return (0, Vec::new());
+        }
+        let bonus = (0..n).map(|j| fzf_bonus(chars, j)).collect::<Vec<i32>>();
+
+        // score[i][j]: best score with pattern[i] matched at chars[j]
+        let mut score = vec![vec![SCORE_NONE; n]; m];
+        // bonus of the first char of consecutive matches ending at (i, j)
+        let mut run_bonus = vec![vec![0; n]; m];
+        // index in chars where pattern[i - 1] is matched
+        let mut parent = vec![vec![0; n]; m];
+
+        for i in 0..m {
+            // best (score, index) of previous row, with gap penalty
+            let mut gap: Option<(i32, usize)> = None;
+            for j in i..n {
+                if i > 0 && j >= 2 {
+                    let prev = score[i - 1][j - 2];
+                    let extended = gap.map(|(s, k)| (s + SCORE_GAP_EXTENSION, k));
+                    let started = if prev > SCORE_NONE {
+                        Some((prev + SCORE_GAP_START, j - 2))
+                    } else {
+                        None
+                    };
+                    gap = match (extended, started) {
+                        (Some(a), Some(b)) => Some(if b.0 >= a.0 { b } else { a }),
+                        (a, b) => a.or(b),
+                    };
+                }
+                if key.char_at(j, casesensitive) != pattern[i] {
+                    continue;
+                }
+                if i == 0 {
+                    score[i][j] = SCORE_MATCH + bonus[j] * BONUS_FIRST_CHAR_MULTIPLIER;
+                    run_bonus[i][j] = bonus[j];
+                    continue;
+                }
+                if j > 0 && score[i - 1][j - 1] > SCORE_NONE {
+                    let b = bonus[j].max(run_bonus[i - 1][j - 1]).max(BONUS_CONSECUTIVE);
+                    score[i][j] = score[i - 1][j - 1] + SCORE_MATCH + b;
+                    run_bonus[i][j] = run_bonus[i - 1][j - 1].max(bonus[j]);
+                    parent[i][j] = j - 1;
+                }
+                if let Some((s, k)) = gap {
+                    if s + SCORE_MATCH + bonus[j] > score[i][j] {
+                        score[i][j] = s + SCORE_MATCH + bonus[j];
+                        run_bonus[i][j] = bonus[j];
+                        parent[i][j] = k;
+                    }
+                }
+            }
+        }
+
+        let best = (0..n)
+            .filter(|&j| score[m - 1][j] > SCORE_NONE)
+            .max_by_key(|&j| (score[m - 1][j], -(j as i64)));
+        let mut j = match best {
+            Some(j) => j,
+            None => return (0, Vec::new()),
+        };
+        let final_score = score[m - 1][j].max(1);
+        let mut indices = vec![j];
+        for i in (1..m).rev() {
+            j = parent[i][j];
+            indices.push(j);
+        }
+        indices.reverse();
+        (final_score, original_positions(chars, indices.into_iter()))
+    }
+}
+
+pub struct PrefixScorer;
+
+impl Scorer for PrefixScorer {
+    fn score_key(&self, key: &SearchKey, pattern: &str, casesensitive: bool) -> (i32, Vec<usize>) {
+        let pattern = pattern_chars(pattern, casesensitive);
+        if pattern.is_empty() {
+            return (0, Vec::new());
+        }
+        let chars = &key.chars;
+        let len = pattern.len() as i32;
+        // prefer prefix of the whole text, then the first word
+        find_runs(key, &pattern, casesensitive)
+            .filter(|&start| is_boundary(chars, start))
+            .map(|start| {
+                let score = if chars[start].1 == 0 {
+                    len * 4
+                } else {
+                    len * 2
+                };
+                (score, start)
+            })
+            .max_by_key(|&(score, start)| (score, -(start as i64)))
+            .map(|(score, start)| {
+                (
+                    score,
+                    original_positions(chars, start..start + pattern.len()),
+                )
+            })
+            .unwrap_or((0, Vec::new()))
+    }
+}
+
+pub struct SubstringScorer;
+
+impl Scorer for SubstringScorer {
+    fn score_key(&self, key: &SearchKey, pattern: &str, casesensitive: bool) -> (i32, Vec<usize>) {
+        let pattern = pattern_chars(pattern, casesensitive);
+        if pattern.is_empty() {
+            return (0, Vec::new());
+        }
+        let chars = &key.chars;
+        let len = pattern.len() as i32;
+        // prefer matches at word boundary, and at the beginning
+        find_runs(key, &pattern, casesensitive)
+            .map(|start| {
+                let mut score = len * 2;
+                if is_boundary(chars, start) {
+                    score += len;
+                }
+                if chars[start].1 == 0 {
+                    score += len;
+                }
+                (score, start)
+            })
+            .max_by_key(|&(score, start)| (score, -(start as i64)))
+            .map(|(score, start)| {
+                (
+                    score,
+                    original_positions(chars, start..start + pattern.len()),
+                )
+            })
+            .unwrap_or((0, Vec::new()))
+    }
+}
+
+/// Edit distance (insertions, deletions, substitutions and transpositions of
+/// adjacent chars) between `pattern` and its closest substring in `key`, None
+/// if greater than `max_distance`. Used as fallback when no item matches
+/// strictly, e.g. for "gogole" to match "Google".
+pub fn typo_distance(key: &SearchKey, pattern: &str, max_distance: usize) -> Option<usize> {
+    let pattern = pattern_chars(pattern, false);
+    let m = pattern.len();
+    if m == 0 || key.len() + max_distance < m {
+        return None;
+    }
+    // columns of distances of pattern prefixes, for text ending at j-2, j-1, j
+    let mut before: Vec<usize> = (0..=m).collect();
+    let mut last: Vec<usize> = (0..=m).collect();
+    let mut cur: Vec<usize> = vec![0; m + 1];
+    let mut best = m;
+    for j in 1..=key.len() {
+        let text_ch = key.char_at(j - 1, false);
+        cur[0] = 0;
+        for i in 1..=m {
+            let cost = if pattern[i - 1] == text_ch { 0 } else { 1 };
+            cur[i] = (last[i - 1] + cost).min(last[i] + 1).min(cur[i - 1] + 1);
+            if i > 1
+                && j > 1
+                && pattern[i - 1] == key.char_at(j - 2, false)
+                && pattern[i - 2] == text_ch
+            {
+                cur[i] = cur[i].min(before[i - 2] + 1);
+            }
+        }
+        best = best.min(cur[m]);
+        std::mem::swap(&mut before, &mut last);
+        std::mem::swap(&mut last, &mut cur);
+    }
+    Some(best).filter(|&x| x <= max_distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [ScorerKind; 4] = [
+        ScorerKind::Fuzzy,
+        ScorerKind::Fzf,
+        ScorerKind::Prefix,
+        ScorerKind::Substring,
+    ];
+
+    /// Properties every scorer should have
+    fn conformance(scorer: &dyn Scorer) {
+        let s = |text, pattern| scorer.score(text, pattern, false);
+        assert!(s("hello world", "hell") > 0);
+        assert!(s("hello world", "hell") > s("hello world", "hwld"));
+        assert!(s("hello world", "hww") == 0);
+        assert!(s("Hello World", "world") > 0);
+        assert!(s("Hello World", "hello") > 0);
+        assert!(scorer.score("Hello World", "hello", true) == 0);
+        assert!(s("", "hw") == 0);
+        assert!(s("hello", "") == 0);
+        assert!(s("你好 世界", "ni") > 0);
+        assert!(s("你好 世界", "世界") > 0);
+        assert!(s("Crème Brûlée", "creme") > 0);
+
+        let p = |text, pattern| scorer.score_positions(text, pattern, false).1;
+        assert_eq!(p("hello world", "hell"), vec![0, 1, 2, 3]);
+        assert_eq!(p("hello world", "wor"), vec![6, 7, 8]);
+        assert_eq!(p("你好 world", "wo"), vec![3, 4]);
+        assert_eq!(p("你好 世界", "shi"), vec![3]);
+        assert!(p("hello", "hx").is_empty());
+    }
+
+    /// Properties of fuzzy (non-contiguous) scorers
+    fn fuzzy_conformance(scorer: &dyn Scorer) {
+        let s = |text, pattern| scorer.score(text, pattern, false);
+        assert!(s("hello world", "hw") > 0);
+        assert!(s("hello world", "hw") > s("hello world", "hl"));
+        assert!(s("Hello World", "hw") > 0);
+        assert!(scorer.score("Hello World", "hw", true) == 0);
+        assert!(s("Hello World", "helloworld") > s("Hello World", "hello"));
+        assert!(s("你好 世界", "nhsj") > 0);
+        assert!(s("你好 世界", "你世") > 0);
+
+        let p = |text, pattern| scorer.score_positions(text, pattern, false).1;
+        assert_eq!(p("hello world", "hw"), vec![0, 6]);
+        assert_eq!(p("你好 世界", "nihsj"), vec![0, 1, 3, 4]);
+        assert_eq!(p("你好 world", "hw"), vec![1, 3]);
+    }
+
+    #[test]
+    fn conformance_test() {
+        for kind in ALL.iter() {
+            conformance(kind.scorer());
+        }
+        fuzzy_conformance(ScorerKind::Fuzzy.scorer());
+        fuzzy_conformance(ScorerKind::Fzf.scorer());
+    }
+
+    #[test]
+    fn typo_distance_test() {
+        let d = |text, pattern, max| typo_distance(&search_keys(text)[0], pattern, max);
+        assert_eq!(d("Google Chrome", "gogole", 2), Some(1));
+        assert_eq!(d("Google Chrome", "chrme", 2), Some(1));
+        assert_eq!(d("Google Chrome", "goggle", 2), Some(1));
+        assert_eq!(d("Google Chrome", "google", 2), Some(0));
+        assert_eq!(d("Google Chrome", "gogolc", 1), None);
+        assert_eq!(d("Google Chrome", "gogolc", 2), Some(2));
+        assert_eq!(d("abc", "abcdef", 2), None);
+    }
+
+    #[test]
+    fn scorer_test() {
+        let fzf = ScorerKind::Fzf.scorer();
+        // best alignment instead of the first one
+        assert_eq!(fzf.score_positions("axxbxx ab", "ab", false).1, vec![7, 8]);
+        assert!(fzf.score("foo_bar", "fb", false) > fzf.score("foobar", "fb", false));
+
+        let prefix = ScorerKind::Prefix.scorer();
+        assert!(prefix.score("hello world", "ello", false) == 0);
+        assert!(
+            prefix.score("hello world", "hel", false) > prefix.score("world hello", "hel", false)
+        );
+        assert_eq!(
+            prefix.score_positions("fooBar", "bar", false).1,
+            vec![3, 4, 5]
+        );
+
+        let substring = ScorerKind::Substring.scorer();
+        assert!(substring.score("hello world", "ello", false) > 0);
+        assert!(
+            substring.score("hello world", "wor", false)
+                > substring.score("hello world", "orl", false)
+        );
+    }
+}
@@ -0,0 +1,440 @@
+// Transliteration of non-ascii text for searching
+//
+// Text is searched as several keys: the transliterated text (pinyin of
+// hanzi, romaji of kana, romanized Cyrillic and Greek, diacritics removed),
+// the original text and pinyin initials of hanzi. Each kind of conversion
+// can be toggled by `[core] transliteration` in config.
+
+extern crate pinyin;
+extern crate unicode_normalization;
+
+use self::unicode_normalization::char::{decompose_canonical, is_combining_mark};
+use crate::mcore::fuzzymatch::SearchKey;
+use std::{collections::HashMap, sync::RwLock};
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct Transliteration {
+    /// Match original (CJK and other non-ascii) characters directly
+    pub cjk: bool,
+    /// Match hanzi by pinyin
+    pub pinyin: bool,
+    /// Match hanzi by initials of pinyin (e.g. "nh" for "你好")
+    pub pinyin_initials: bool,
+    /// Match hanzi by all readings instead of the most common one
+    pub pinyin_polyphones: bool,
+    /// Match kana by romaji (Hepburn)
+    pub kana: bool,
+    /// Match Cyrillic letters by romanization
+    pub cyrillic: bool,
+    /// Match Greek letters by romanization
+    pub greek: bool,
+    /// Match letters with diacritics by base letters (e.g. "e" for "é")
+    pub diacritics: bool,
+}
+
+impl Default for Transliteration {
+    fn default() -> Transliteration {
+        Transliteration {
+            cjk: true,
+            pinyin: true,
+            pinyin_initials: true,
+            pinyin_polyphones: false,
+            kana: true,
+            cyrillic: true,
+            greek: true,
+            diacritics: true,
+        }
+    }
+}
+
+lazy_static! {
+    static ref CONFIG: RwLock<Transliteration> = RwLock::new(Transliteration::default());
+
+    /// All readings of chars, converting is slow (regex compiled for every call)
+    static ref PINYIN_CACHE: RwLock<HashMap<char, Vec<String>>> = RwLock::new(HashMap::new());
+}
+
+/// Set transliteration used by `search_keys`
+pub fn configure(config: Transliteration) {
+    *CONFIG.write().unwrap() = config;
+}
+
+/// Pinyin readings of a char (most common first), empty if not a hanzi
+fn char_pinyin(c: char) -> Vec<String> {
+    if let Some(readings) = PINYIN_CACHE.read().unwrap().get(&c) {
+        return readings.clone();
+    }
+    let args = pinyin::Args {
+        style: pinyin::Style::Normal,
+        heteronym: true,
+    };
+    let mut readings: Vec<String> = Vec::new();
+    for reading in pinyin::pinyin(&c.to_string(), &args).into_iter().flatten() {
+        if !readings.contains(&reading) {
+            readings.push(reading);
+        }
+    }
+    PINYIN_CACHE.write().unwrap().insert(c, readings.clone());
+    readings
+}
+
+/// Romaji of hiragana from U+3041 to U+3096, katakana are shifted by 0x60
+const KANA_ROMAJI: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o", // ぁ-お
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go", // か-ご
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo", // さ-ぞ
+    "ta", "da", "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do", // た-ど
+    "na", "ni", "nu", "ne", "no", // な-の
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo",
+    "po", // は-ぽ
+    "ma", "mi", "mu", "me", "mo", // ま-も
+    "ya", "ya", "yu", "yu", "yo", "yo", // ゃ-よ
+    "ra", "ri", "ru", "re", "ro", // ら-ろ
+    "wa", "wa", "i", "e", "o", "n", // ゎ-ん
+    "vu", "ka", "ke", // ゔ-ゖ
+];
+
+const SMALL_TSU: char = '\u{3063}';
+const PROLONGED_SOUND: char = '\u{30FC}';
+
+/// Kana as hiragana
+fn hiragana(c: char) -> Option<char> {
+    match c {
+        '\u{3041}'..='\u{3096}' => Some(c),
+        '\u{30A1}'..='\u{30F6}' => std::char::from_u32(c as u32 - 0x60),
+        _ => None,
+    }
+}
+
+/// Small ゃ, ゅ or ょ, combined with the previous syllable
+fn is_small_y(c: char) -> bool {
+    matches!(c, '\u{3083}' | '\u{3085}' | '\u{3087}')
+}
+
+fn cyrillic_romaji(c: char) -> Option<&'static str> {
+    Some(match c {
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' => "g",
+        'д' => "d",
+        'е' | 'ё' | 'э' => "e",
+        'ж' => "zh",
+        'з' => "z",
+        'и' | 'і' => "i",
+        'й' | 'ы' => "y",
+        'к' => "k",
+        'л' => "l",
+        'м' => "m",
+        'н' => "n",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'у' | 'ў' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+        'є' => "ye",
+        'ї' => "yi",
+        _ => return None,
+    })
+}
+
+fn greek_romaji(c: char) -> Option<&'static str> {
+    Some(match c {
+        'α' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' => "e",
+        'ζ' => "z",
+        'η' | 'ι' => "i",
+        'θ' => "th",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' | 'ω' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        _ => return None,
+    })
+}
+
+/// Letters without decomposition
+fn special_latin(c: char) -> Option<&'static str> {
+    Some(match c {
+        'ß' => "ss",
+        'ẞ' => "SS",
+        'æ' => "ae",
+        'Æ' => "AE",
+        'œ' => "oe",
+        'Œ' => "OE",
+        'ø' => "o",
+        'Ø' => "O",
+        'ł' => "l",
+        'Ł' => "L",
+        'đ' | 'ð' => "d",
+        'Đ' | 'Ð' => "D",
+        'þ' => "th",
+        'Þ' => "TH",
+        'ı' => "i",
+        _ => return None,
+    })
+}
+
+/// Base char of `c` without combining marks
+fn base_char(c: char) -> char {
+    let mut base = c;
+    decompose_canonical(c, |d| {
+        if !is_combining_mark(d) && base == c {
+            base = d;
+        }
+    });
+    base
+}
+
+/// Romanization of a Cyrillic or Greek letter, capitalized if `c` is uppercase
+fn romanize(c: char, config: &Transliteration) -> Option<String> {
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    let cyrillic = if config.cyrillic {
+        cyrillic_romaji(lower)
+    } else {
+        None
+    };
+    let romaji = match cyrillic {
+        Some(romaji) => romaji,
+        None if config.greek => greek_romaji(base_char(lower))?,
+        None => return None,
+    };
+    if lower == c {
+        return Some(romaji.into());
+    }
+    let mut chars = romaji.chars();
+    Some(match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    })
+}
+
+/// Letter with diacritics as ascii letters
+fn fold_diacritics(c: char) -> Option<String> {
+    if let Some(s) = special_latin(c) {
+        return Some(s.into());
+    }
+    let mut folded = String::new();
+    decompose_canonical(c, |d| {
+        if !is_combining_mark(d) {
+            folded.push(d);
+        }
+    });
+    if folded.is_empty() || folded.len() != folded.chars().count() || folded == c.to_string() {
+        None
+    } else {
+        Some(folded)
+    }
+}
+
+/// Chars of transliterated `text`, along with the index (in chars) of original
+/// char. Pinyin of each hanzi is surrounded by spaces, or replaced by its
+/// initial if `initials`.
+fn transliterate(text: &str, config: &Transliteration, initials: bool) -> Vec<(char, usize)> {
+    let mut out: Vec<(char, usize)> = Vec::with_capacity(text.len());
+    let push = |out: &mut Vec<(char, usize)>, s: &str, idx: usize| {
+        out.extend(s.chars().map(|c| (c, idx)));
+    };
+    // whether last chars are romaji of kana, and index of pending small tsu
+    let mut after_kana = false;
+    let mut small_tsu: Option<usize> = None;
+
+    for (idx, c) in text.chars().enumerate() {
+        if c.is_ascii() {
+            out.push((c, idx));
+            after_kana = false;
+            continue;
+        }
+
+        if config.kana {
+            if let Some(h) = hiragana(c) {
+                let romaji = KANA_ROMAJI[(h as u32 - 0x3041) as usize];
+                if h == SMALL_TSU {
+                    small_tsu = Some(idx);
+                } else if is_small_y(h) && after_kana && out.last().map(|x| x.0) == Some('i') {
+                    // きゃ -> kya, しゃ -> sha
+                    out.pop();
+                    let tail: String = out.iter().rev().take(2).map(|x| x.0).collect();
+                    let vowel = &romaji[1..];
+                    if tail == "hs" || tail == "hc" || tail.starts_with('j') {
+                        push(&mut out, vowel, idx);
+                    } else {
+                        push(&mut out, romaji, idx);
+                    }
+                } else {
+                    if let (Some(tsu_idx), Some(first)) = (small_tsu, romaji.chars().next()) {
+                        out.push((if romaji.starts_with("ch") { 't' } else { first }, tsu_idx));
+                    }
+                    push(&mut out, romaji, idx);
+                }
+                if h != SMALL_TSU {
+                    small_tsu = None;
+                }
+                after_kana = true;
+                continue;
+            }
+            if c == PROLONGED_SOUND && after_kana {
+                if let Some(&(last, _)) = out.last() {
+                    if "aeiou".contains(last) {
+                        out.push((last, idx));
+                    }
+                }
+                continue;
+            }
+        }
+        after_kana = false;
+        small_tsu = None;
+
+        if config.pinyin {
+            let readings = char_pinyin(c);
+            if !readings.is_empty() {
+                if initials {
+                    push(&mut out, &readings[0][..1], idx);
+                    continue;
+                }
+                let count = if config.pinyin_polyphones {
+                    readings.len()
+                } else {
+                    1
+                };
+                out.push((' ', idx));
+                for reading in &readings[..count] {
+                    push(&mut out, reading, idx);
+                    out.push((' ', idx));
+                }
+                continue;
+            }
+        }
+
+        if let Some(s) = romanize(c, config) {
+            push(&mut out, &s, idx);
+        } else if config.diacritics && is_combining_mark(c) {
+            // already decomposed text, drop the mark
+        } else if let Some(s) = fold_diacritics(c).filter(|_| config.diacritics) {
+            push(&mut out, &s, idx);
+        } else {
+            out.push((c, idx));
+        }
+    }
+    out
+}
+
+/// Keys to search `text` in, using transliteration set by `configure`
+pub fn search_keys(text: &str) -> Vec<SearchKey> {
+    search_keys_with(text, &CONFIG.read().unwrap())
+}
+
+/// Keys to search `text` in: the transliterated text, the original text and
+/// pinyin initials, each one only if enabled and different from the others
+pub fn search_keys_with(text: &str, config: &Transliteration) -> Vec<SearchKey> {
+    let mut keys = vec![SearchKey::from_chars(transliterate(text, config, false))];
+    if text.is_ascii() {
+        return keys;
+    }
+    if config.cjk {
+        keys.push(SearchKey::from_chars(
+            text.chars().enumerate().map(|(idx, c)| (c, idx)).collect(),
+        ));
+    }
+    if config.pinyin && config.pinyin_initials {
+        keys.push(SearchKey::from_chars(transliterate(text, config, true)));
+    }
+    let mut unique: Vec<SearchKey> = Vec::with_capacity(keys.len());
+    for key in keys {
+        if !unique.iter().any(|x| x.chars == key.chars) {
+            unique.push(key);
+        }
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(text: &str, config: &Transliteration) -> Vec<String> {
+        search_keys_with(text, config)
+            .iter()
+            .map(|key| key.chars.iter().map(|x| x.0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn transliterate_test() {
+        let config = Transliteration::default();
+        assert_eq!(strings("hello", &config), vec!["hello"]);
+        assert_eq!(
+            strings("你好 world", &config),
+            vec![" ni  hao  world", "你好 world", "nh world"]
+        );
+        assert_eq!(strings("Café", &config), vec!["Cafe", "Café"]);
+        assert_eq!(strings("Straße", &config)[0], "Strasse");
+        assert_eq!(strings("Cafe\u{301}", &config)[0], "Cafe");
+        assert_eq!(strings("Москва", &config)[0], "Moskva");
+        assert_eq!(strings("Αθήνα", &config)[0], "Athina");
+        assert_eq!(strings("すし", &config)[0], "sushi");
+        assert_eq!(strings("とうきょう", &config)[0], "toukyou");
+        assert_eq!(strings("しゃしん", &config)[0], "shashin");
+        assert_eq!(strings("きっぷ", &config)[0], "kippu");
+        assert_eq!(strings("ラーメン", &config)[0], "raamen");
+        assert_eq!(KANA_ROMAJI.len(), 0x3096 - 0x3041 + 1);
+
+        let polyphones = Transliteration {
+            pinyin_polyphones: true,
+            ..config
+        };
+        assert!(strings("长", &polyphones)[0].contains(" zhang "));
+        assert!(strings("长", &polyphones)[0].contains(" chang "));
+
+        let disabled = Transliteration {
+            cjk: false,
+            pinyin: false,
+            kana: false,
+            diacritics: false,
+            ..config
+        };
+        assert_eq!(strings("Café すし", &disabled), vec!["Café すし"]);
+    }
+
+    #[test]
+    fn positions_test() {
+        let key = &search_keys_with("きゃべつ", &Transliteration::default())[0];
+        assert_eq!(
+            key.chars,
+            vec![
+                ('k', 0),
+                ('y', 1),
+                ('a', 1),
+                ('b', 2),
+                ('e', 2),
+                ('t', 3),
+                ('s', 3),
+                ('u', 3)
+            ]
+        );
+    }
+}